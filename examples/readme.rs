@@ -3,10 +3,12 @@ use std::thread;
 use std::time::Duration;
 
 fn main() {
-    Keyboard::A.bind(|_| {
-        println!("A pressed, sending B");
-        Keyboard::B.click();
-    });
+    Keyboard::A
+        .bind(|_| {
+            println!("A pressed, sending B");
+            Keyboard::B.click();
+        })
+        .forget();
     mki::bind_any_key(Action::handle_kb(|key| {
         use Keyboard::*;
         if matches!(key, S | L | O | W | LeftShift | LeftControl | B) {
@@ -14,23 +16,28 @@ fn main() {
         } else {
             println!("Some key pressed pressed: {:?}", key);
         }
-    }));
+    }))
+    .forget();
     mki::bind_any_button(Action::handle_mouse(|button| {
         println!("Mouse button pressed {:?}", button);
-    }));
+    }))
+    .forget();
     mki::register_hotkey(&[Keyboard::LeftControl, Keyboard::B], || {
         println!("Ctrl+B Pressed")
-    });
+    })
+    .forget();
     mki::bind_key(
         Keyboard::S,
         Action::sequencing_kb(|_| {
             Sequence::text("LLLLLow").unwrap().send();
             thread::sleep(Duration::from_secs(1));
         }),
-    );
+    )
+    .forget();
 
     // This binds action to a W key,
-    // that W press will not be sent to the following services ( only on windows )
+    // that W press will not be sent to the following services
+    // ( on Linux only when the devices are grabbed, see `LinuxBackend::grab` )
     // whenever Caps Lock is toggled
     // Action will be executed on separate thread.
     bind_key(
@@ -49,7 +56,8 @@ fn main() {
             sequencer: false,
             defer: true,
         },
-    );
+    )
+    .forget();
 
     thread::sleep(Duration::from_secs(100));
 }
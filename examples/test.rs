@@ -7,46 +7,56 @@ fn main() {
     use Keyboard::*;
     bind_any_key(Action::handle_kb(|key| {
         println!("Pressed: {:?}", key);
-    }));
+    }))
+    .forget();
     A.bind(|_| {
         println!("AAAAAAnd we have a winner.");
-    });
+    })
+    .forget();
 
     S.act_on(Action::sequencing_kb(|_| {
         println!("\nOkay mimicking a very slow operation... (1s sleep)");
         thread::sleep(Duration::from_millis(1000));
         println!("\nOkay action done (100ms sleep)");
         thread::sleep(Duration::from_millis(100));
-    }));
+    }))
+    .forget();
 
     R.bind(|_| {
         println!("R Pressed sending Q");
         Q.click();
-    });
+    })
+    .forget();
 
-    Mouse::Left.bind(|_| {
-        println!("Left Mouse button pressed");
-    });
+    Mouse::Left
+        .bind(|_| {
+            println!("Left Mouse button pressed");
+        })
+        .forget();
 
-    #[cfg(target_os = "windows")] // Not sure how to detect double on linux
-    Mouse::DoubleLeft.bind(|_| {
-        println!("Double Left Click Mouse");
-    });
+    Mouse::DoubleLeft
+        .bind(|_| {
+            println!("Double Left Click Mouse");
+        })
+        .forget();
 
-    Mouse::Right.bind(|_| {
-        println!("Right Mouse button pressed");
-    });
+    Mouse::Right
+        .bind(|_| {
+            println!("Right Mouse button pressed");
+        })
+        .forget();
 
     O.bind(|_| loop {
         println!("Observing T: {}", T.is_pressed());
         thread::sleep(Duration::from_secs(1));
-    });
+    })
+    .forget();
 
     for key in [T, H, I, S, Space, A, Space, T, E, S, T].iter() {
         key.click();
     }
 
-    register_hotkey(&[LeftControl, U], || println!("Ctrl+U pressed"));
+    register_hotkey(&[LeftControl, U], || println!("Ctrl+U pressed")).forget();
 
     Mouse::track(|x, y| {
         static COUNTER: AtomicI64 = AtomicI64::new(0);
@@ -68,6 +78,7 @@ fn main() {
         Sequence::text("\nWill now type...:\nmini. very, mini1\nHello World.\nAnswer is: 42")
             .unwrap()
             .send();
-    });
+    })
+    .forget();
     thread::sleep(Duration::from_secs(100));
 }
@@ -0,0 +1,526 @@
+use crate::context::context;
+use crate::details::Registry;
+use crate::{Error, Event, InhibitEvent, Keyboard, Mouse};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Platform layer used to inject events and to listen for them.
+/// Linux and Windows implementations are provided and picked by default,
+/// `MockBackend` can be installed with `set_backend` or `ContextBuilder::backend`
+/// to run without any real input devices.
+pub trait Backend: Send + Sync {
+    /// Send an event to Press this key
+    fn press(&self, key: Keyboard);
+
+    /// Send an event to Release this key
+    fn release(&self, key: Keyboard);
+
+    /// Send an event to Click (Press + Release) this key
+    fn click(&self, key: Keyboard) {
+        self.press(key);
+        self.release(key);
+    }
+
+    /// Type given character however the platform allows it, independently of the keyboard layout.
+    fn type_char(&self, c: char);
+
+    /// Whether this KeyboardKey is toggled, applies for some buttons such as Caps Lock
+    fn is_toggled(&self, key: Keyboard) -> bool;
+
+    /// Whether the button can be injected, `Mouse::try_press` and the like fail with `Error::Unsupported` otherwise.
+    fn supports_button(&self, _button: Mouse) -> bool {
+        true
+    }
+
+    /// Send an event to Press this Button
+    fn press_button(&self, button: Mouse);
+
+    /// Send an event to Release this Button
+    fn release_button(&self, button: Mouse);
+
+    /// Send an event to Click (Press + Release) this Button
+    fn click_button(&self, button: Mouse) {
+        self.press_button(button);
+        self.release_button(button);
+    }
+
+    /// Move the pointer to the given position.
+    fn move_to(&self, x: i32, y: i32);
+
+    /// Move the pointer to the given position in pixels, same units as `position` reports.
+    /// Defaults to `move_to`, backends whose `move_to` takes other units convert.
+    fn move_to_pixel(&self, x: i32, y: i32) {
+        self.move_to(x, y);
+    }
+
+    /// Move the pointer by the given offset.
+    fn move_by(&self, x: i32, y: i32);
+
+    /// Rotate the wheel by given clicks, positive `dy` scrolls up, positive `dx` scrolls right.
+    fn scroll(&self, dx: i32, dy: i32);
+
+    /// Current pointer position in pixels.
+    fn position(&self) -> (i32, i32);
+
+    /// Fallible version of `press`, the default one cannot fail.
+    fn try_press(&self, key: Keyboard) -> Result<(), Error> {
+        self.press(key);
+        Ok(())
+    }
+
+    /// Fallible version of `release`, the default one cannot fail.
+    fn try_release(&self, key: Keyboard) -> Result<(), Error> {
+        self.release(key);
+        Ok(())
+    }
+
+    fn try_click(&self, key: Keyboard) -> Result<(), Error> {
+        self.try_press(key)?;
+        self.try_release(key)
+    }
+
+    /// Fallible version of `press_button`, the default one fails only for unsupported buttons.
+    fn try_press_button(&self, button: Mouse) -> Result<(), Error> {
+        if !self.supports_button(button) {
+            return Err(Error::Unsupported(format!(
+                "mouse button {} cannot be injected on this platform",
+                button
+            )));
+        }
+        self.press_button(button);
+        Ok(())
+    }
+
+    /// Fallible version of `release_button`, the default one fails only for unsupported buttons.
+    fn try_release_button(&self, button: Mouse) -> Result<(), Error> {
+        if !self.supports_button(button) {
+            return Err(Error::Unsupported(format!(
+                "mouse button {} cannot be injected on this platform",
+                button
+            )));
+        }
+        self.release_button(button);
+        Ok(())
+    }
+
+    fn try_click_button(&self, button: Mouse) -> Result<(), Error> {
+        self.try_press_button(button)?;
+        self.try_release_button(button)
+    }
+
+    /// Fallible version of `move_to`, the default one cannot fail.
+    fn try_move_to(&self, x: i32, y: i32) -> Result<(), Error> {
+        self.move_to(x, y);
+        Ok(())
+    }
+
+    /// Fallible version of `move_by`, the default one cannot fail.
+    fn try_move_by(&self, x: i32, y: i32) -> Result<(), Error> {
+        self.move_by(x, y);
+        Ok(())
+    }
+
+    /// Fallible version of `position`, the default one cannot fail.
+    fn try_position(&self) -> Result<(i32, i32), Error> {
+        Ok(self.position())
+    }
+
+    /// Move the pointer to the given position and click there.
+    fn click_at(&self, x: i32, y: i32, button: Mouse) {
+        self.move_to(x, y);
+        self.click_button(button);
+    }
+
+    /// Listening loop, invoked on the dedicated `mki-lstn` thread.
+    /// Observed events are expected to be passed to the `listener`,
+    /// should return once `Listener::is_listening` turns false.
+    fn listen(&self, listener: Listener);
+
+    /// Invoked on shutdown after the listener stopped listening, wakes up the blocked `listen`.
+    fn stop(&self) {}
+}
+
+#[derive(Clone)]
+/// Passed to `Backend::listen`, forwards the observed events to the binds of the owning `Context`.
+pub struct Listener {
+    registry: Arc<Registry>,
+    listening: Arc<AtomicBool>,
+}
+
+impl Listener {
+    pub(crate) fn new(registry: Arc<Registry>, listening: Arc<AtomicBool>) -> Self {
+        Listener {
+            registry,
+            listening,
+        }
+    }
+
+    /// Whether the owning `Context` still wants the events, `listen` should return otherwise.
+    pub fn is_listening(&self) -> bool {
+        self.listening.load(Ordering::Relaxed)
+    }
+
+    /// Key was pressed, returns whether the event should be inhibited.
+    pub fn key_down(&self, key: Keyboard) -> InhibitEvent {
+        self.registry.event_down(Event::Keyboard(key))
+    }
+
+    /// Key was released.
+    pub fn key_up(&self, key: Keyboard) -> InhibitEvent {
+        self.registry.event_up(Event::Keyboard(key))
+    }
+
+    /// Key was pressed by this library, see `Backend::press`.
+    /// Such events are passed to the binds but are never remapped.
+    pub fn injected_key_down(&self, key: Keyboard) -> InhibitEvent {
+        self.registry.dispatch_down(Event::Keyboard(key))
+    }
+
+    /// Key was released by this library, see `injected_key_down`.
+    pub fn injected_key_up(&self, key: Keyboard) -> InhibitEvent {
+        self.registry.dispatch_up(Event::Keyboard(key))
+    }
+
+    /// Button was pressed, returns whether the event should be inhibited.
+    pub fn button_down(&self, button: Mouse) -> InhibitEvent {
+        self.registry.event_down(Event::Mouse(button))
+    }
+
+    /// Button was released.
+    pub fn button_up(&self, button: Mouse) -> InhibitEvent {
+        self.registry.event_up(Event::Mouse(button))
+    }
+
+    /// Button was clicked, both down and up are reported.
+    pub fn button_click(&self, button: Mouse) -> InhibitEvent {
+        self.registry.event_click(Event::Mouse(button))
+    }
+
+    /// Wheel was rotated by given clicks, positive `dy` is up, positive `dx` is right.
+    pub fn scrolled(&self, dx: i32, dy: i32) -> InhibitEvent {
+        self.registry.event_scroll(dx, dy, false)
+    }
+
+    /// Wheel was rotated by this library, scroll binds are skipped so that they can scroll themselves.
+    pub fn injected_scrolled(&self, dx: i32, dy: i32) -> InhibitEvent {
+        self.registry.event_scroll(dx, dy, true)
+    }
+
+    /// Whether anyone is interested in `mouse_moved`, allows skipping the position lookup.
+    pub fn is_tracking_mouse(&self) -> bool {
+        self.registry.is_tracking_enabled()
+    }
+
+    /// Pointer moved to the given position.
+    pub fn mouse_moved(&self, x: i32, y: i32) {
+        self.registry.update_mouse_position(x, y)
+    }
+}
+
+/// Replaces the backend of the default context used for injection and listening.
+/// Best called before anything is bound, otherwise the listener is restarted.
+pub fn set_backend(backend: impl Backend + 'static) {
+    context().set_backend(backend)
+}
+
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+/// Event that was injected through the `MockBackend`.
+pub enum Injected {
+    Press(Keyboard),
+    Release(Keyboard),
+    Char(char),
+    PressButton(Mouse),
+    ReleaseButton(Mouse),
+    MoveTo(i32, i32),
+    MoveBy(i32, i32),
+    Scroll(i32, i32),
+}
+
+#[derive(Default)]
+struct MockState {
+    injected: Vec<Injected>,
+    toggled: HashSet<Keyboard>,
+    unsupported: HashSet<Mouse>,
+    position: (i32, i32),
+    listener: Option<Listener>,
+}
+
+#[derive(Clone, Default)]
+/// In memory backend, nothing reaches the OS.
+/// Records every injected event and allows feeding synthetic input as if it was observed.
+/// Clones share the same state so a clone can be kept around after passing it to the `Context`.
+/// Synthetic input is dropped unless the owning `Context` is listening,
+/// as the listener thread starts asynchronously use `wait_for_listener` after `Context::start`.
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+    listening: Arc<Condvar>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All the events injected so far, oldest first.
+    pub fn injected(&self) -> Vec<Injected> {
+        self.state.lock().unwrap().injected.clone()
+    }
+
+    /// Forget the events injected so far.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().injected.clear();
+    }
+
+    /// Sets what `is_toggled` should report for given key.
+    pub fn set_toggled(&self, key: Keyboard, toggled: bool) {
+        let toggles = &mut self.state.lock().unwrap().toggled;
+        if toggled {
+            toggles.insert(key);
+        } else {
+            toggles.remove(&key);
+        }
+    }
+
+    /// Sets what `supports_button` should report for given button, all are supported by default.
+    pub fn set_supported(&self, button: Mouse, supported: bool) {
+        let unsupported = &mut self.state.lock().unwrap().unsupported;
+        if supported {
+            unsupported.remove(&button);
+        } else {
+            unsupported.insert(button);
+        }
+    }
+
+    /// Simulate the key being pressed by the user.
+    pub fn key_down(&self, key: Keyboard) -> InhibitEvent {
+        self.listener()
+            .map(|listener| listener.key_down(key))
+            .unwrap_or(InhibitEvent::No)
+    }
+
+    /// Simulate the key being released by the user.
+    pub fn key_up(&self, key: Keyboard) -> InhibitEvent {
+        self.listener()
+            .map(|listener| listener.key_up(key))
+            .unwrap_or(InhibitEvent::No)
+    }
+
+    /// Simulate the button being pressed by the user.
+    pub fn button_down(&self, button: Mouse) -> InhibitEvent {
+        self.listener()
+            .map(|listener| listener.button_down(button))
+            .unwrap_or(InhibitEvent::No)
+    }
+
+    /// Simulate the button being released by the user.
+    pub fn button_up(&self, button: Mouse) -> InhibitEvent {
+        self.listener()
+            .map(|listener| listener.button_up(button))
+            .unwrap_or(InhibitEvent::No)
+    }
+
+    /// Simulate the user rotating the wheel.
+    pub fn scrolled(&self, dx: i32, dy: i32) -> InhibitEvent {
+        self.listener()
+            .map(|listener| listener.scrolled(dx, dy))
+            .unwrap_or(InhibitEvent::No)
+    }
+
+    /// Simulate the user moving the pointer.
+    pub fn mouse_moved(&self, x: i32, y: i32) {
+        self.state.lock().unwrap().position = (x, y);
+        if let Some(listener) = self.listener() {
+            listener.mouse_moved(x, y);
+        }
+    }
+
+    /// Blocks until the owning `Context` is listening, false if it did not come up within `timeout`.
+    pub fn wait_for_listener(&self, timeout: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        let (state, _) = self
+            .listening
+            .wait_timeout_while(state, timeout, |state| state.listener.is_none())
+            .unwrap();
+        state.listener.is_some()
+    }
+
+    fn listener(&self) -> Option<Listener> {
+        self.state.lock().unwrap().listener.clone()
+    }
+
+    fn record(&self, event: Injected) {
+        self.state.lock().unwrap().injected.push(event);
+    }
+}
+
+impl Backend for MockBackend {
+    fn press(&self, key: Keyboard) {
+        self.record(Injected::Press(key));
+    }
+
+    fn release(&self, key: Keyboard) {
+        self.record(Injected::Release(key));
+    }
+
+    fn type_char(&self, c: char) {
+        self.record(Injected::Char(c));
+    }
+
+    fn is_toggled(&self, key: Keyboard) -> bool {
+        self.state.lock().unwrap().toggled.contains(&key)
+    }
+
+    fn supports_button(&self, button: Mouse) -> bool {
+        !self.state.lock().unwrap().unsupported.contains(&button)
+    }
+
+    fn press_button(&self, button: Mouse) {
+        self.record(Injected::PressButton(button));
+    }
+
+    fn release_button(&self, button: Mouse) {
+        self.record(Injected::ReleaseButton(button));
+    }
+
+    fn move_to(&self, x: i32, y: i32) {
+        self.state.lock().unwrap().position = (x, y);
+        self.record(Injected::MoveTo(x, y));
+    }
+
+    fn scroll(&self, dx: i32, dy: i32) {
+        self.record(Injected::Scroll(dx, dy));
+    }
+
+    /// Pointer position as moved by `move_to`, `move_by` and `mouse_moved`.
+    fn position(&self) -> (i32, i32) {
+        self.state.lock().unwrap().position
+    }
+
+    fn move_by(&self, x: i32, y: i32) {
+        let mut state = self.state.lock().unwrap();
+        state.position = (state.position.0 + x, state.position.1 + y);
+        state.injected.push(Injected::MoveBy(x, y));
+    }
+
+    fn listen(&self, listener: Listener) {
+        self.state.lock().unwrap().listener = Some(listener);
+        self.listening.notify_all();
+    }
+
+    fn stop(&self) {
+        self.state.lock().unwrap().listener = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Action, Backend, Context, Error, Event, InhibitEvent, Injected, Keyboard, MockBackend,
+        Mouse,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn mock_backend() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+
+        let invoked = Arc::new(AtomicUsize::new(0));
+        let _bind = context.bind_key(Keyboard::B, {
+            let invoked = invoked.clone();
+            Action::callback_kb(move |_| {
+                invoked.fetch_add(1, Ordering::Relaxed);
+            })
+        });
+        mock.key_down(Keyboard::B);
+        assert!(context.is_pressed(Event::Keyboard(Keyboard::B)));
+        mock.key_up(Keyboard::B);
+        assert!(!context.is_pressed(Event::Keyboard(Keyboard::B)));
+        mock.key_down(Keyboard::A);
+        mock.key_up(Keyboard::A);
+        assert_eq!(1, invoked.load(Ordering::Relaxed));
+
+        let backend = context.backend();
+        backend.click(Keyboard::C);
+        backend.click_at(10, 20, Mouse::Left);
+        backend.move_by(1, 1);
+        assert_eq!(
+            vec![
+                Injected::Press(Keyboard::C),
+                Injected::Release(Keyboard::C),
+                Injected::MoveTo(10, 20),
+                Injected::PressButton(Mouse::Left),
+                Injected::ReleaseButton(Mouse::Left),
+                Injected::MoveBy(1, 1),
+            ],
+            mock.injected()
+        );
+        assert_eq!((11, 21), mock.position());
+
+        let tracked = Arc::new(std::sync::Mutex::new(Vec::new()));
+        context.track_mouse({
+            let tracked = tracked.clone();
+            move |x, y| tracked.lock().unwrap().push((x, y))
+        });
+        mock.mouse_moved(5, 6);
+        assert_eq!(vec![(5, 6)], *tracked.lock().unwrap());
+        assert_eq!((5, 6), backend.position());
+
+        let scrolled = Arc::new(AtomicUsize::new(0));
+        let _scroll_bind = context.bind_button(Mouse::ScrollUp, {
+            let scrolled = scrolled.clone();
+            Action::callback_mouse(move |_| {
+                scrolled.fetch_add(1, Ordering::Relaxed);
+            })
+        });
+        mock.scrolled(0, 2);
+        mock.scrolled(0, -1);
+        assert_eq!(2, scrolled.load(Ordering::Relaxed));
+
+        let (tx, rx) = channel();
+        let tx = Mutex::new(tx);
+        let scroll_bind = context.bind_scroll(InhibitEvent::Yes, move |dx, dy| {
+            tx.lock().unwrap().send((dx, dy)).unwrap();
+        });
+        assert!(mock.scrolled(-1, 3).should_inhibit());
+        assert_eq!(Ok((-1, 3)), rx.recv_timeout(Duration::from_secs(1)));
+        assert_eq!(5, scrolled.load(Ordering::Relaxed));
+        drop(scroll_bind);
+        assert!(!mock.scrolled(0, 1).should_inhibit());
+
+        assert!(!backend.is_toggled(Keyboard::CapsLock));
+        mock.set_toggled(Keyboard::CapsLock, true);
+        assert!(backend.is_toggled(Keyboard::CapsLock));
+    }
+
+    #[test]
+    fn unsupported_button() {
+        let mock = MockBackend::new();
+        mock.set_supported(Mouse::Forward, false);
+        assert!(matches!(
+            mock.try_press_button(Mouse::Forward),
+            Err(Error::Unsupported(_))
+        ));
+        assert_eq!(
+            "Unsupported: mouse button Forward cannot be injected on this platform",
+            mock.try_click_button(Mouse::Forward)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(Ok(()), mock.try_click_button(Mouse::Back));
+        assert_eq!(
+            vec![
+                Injected::PressButton(Mouse::Back),
+                Injected::ReleaseButton(Mouse::Back),
+            ],
+            mock.injected()
+        );
+    }
+}
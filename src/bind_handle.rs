@@ -0,0 +1,142 @@
+use crate::details::Registry;
+use crate::{Event, Keyboard, Mouse};
+use std::sync::{Arc, Weak};
+
+pub(crate) enum BindTarget {
+    AnyKey,
+    AnyButton,
+    Scroll,
+    Key(Keyboard),
+    Button(Mouse),
+    /// Binds the event and, when it watches the others, any key and any button under the same id.
+    Gesture(Event),
+    Hotkey,
+    Chords,
+    Leader,
+    /// Bind of a layer other than the base one, wraps `Key`, `Button` or `Hotkey`.
+    Layer(String, Box<BindTarget>),
+    Remap,
+}
+
+/// Returned by every bind, the bind is removed once this handle is dropped.
+/// Use `forget` to keep the bind for the lifetime of the `Context`.
+/// ```
+/// use mki::*;
+///
+/// fn scoped_bind() {
+///   let handle = Keyboard::B.bind(|_| println!("B Pressed"));
+///   // B is bound until here.
+///   drop(handle);
+///   Keyboard::C.bind(|_| println!("C Pressed")).forget();
+/// }
+/// ```
+#[must_use = "the bind is removed as soon as the handle is dropped, use `forget` to keep it"]
+pub struct BindHandle {
+    registry: Weak<Registry>,
+    target: BindTarget,
+    id: u64,
+}
+
+impl BindHandle {
+    pub(crate) fn new(registry: &Arc<Registry>, target: BindTarget, id: u64) -> Self {
+        BindHandle {
+            registry: Arc::downgrade(registry),
+            target,
+            id,
+        }
+    }
+
+    /// Keep the bind, it can still be removed with `remove_key_bind` and the like.
+    pub fn forget(mut self) {
+        self.registry = Weak::new();
+    }
+
+    /// Remove the bind now, same as dropping the handle.
+    pub fn unbind(self) {}
+}
+
+impl Drop for BindHandle {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            registry.unbind(&self.target, self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Action, Context, Keyboard, MockBackend};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn binds_on_the_same_key_coexist() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let invoked = Arc::new(Mutex::new(Vec::new()));
+        let bind = |name: &'static str| {
+            let invoked = invoked.clone();
+            context.bind_key(
+                Keyboard::K,
+                Action::callback_kb(move |_| invoked.lock().unwrap().push(name)),
+            )
+        };
+        let first = bind("first");
+        bind("second").forget();
+        let third = bind("third");
+
+        mock.key_down(Keyboard::K);
+        mock.key_up(Keyboard::K);
+        assert_eq!(vec!["first", "second", "third"], *invoked.lock().unwrap());
+
+        invoked.lock().unwrap().clear();
+        drop(first);
+        third.unbind();
+        mock.key_down(Keyboard::K);
+        mock.key_up(Keyboard::K);
+        assert_eq!(vec!["second"], *invoked.lock().unwrap());
+
+        invoked.lock().unwrap().clear();
+        context.remove_key_bind(Keyboard::K);
+        mock.key_down(Keyboard::K);
+        mock.key_up(Keyboard::K);
+        assert!(invoked.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn any_key_binds_coexist() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let invoked = Arc::new(Mutex::new(Vec::new()));
+        let bind = |name: &'static str| {
+            let invoked = invoked.clone();
+            context.bind_any_key(Action::callback_kb(move |key| {
+                invoked.lock().unwrap().push((name, key))
+            }))
+        };
+        let first = bind("first");
+        bind("second").forget();
+
+        mock.key_down(Keyboard::J);
+        assert_eq!(
+            vec![("first", Keyboard::J), ("second", Keyboard::J)],
+            *invoked.lock().unwrap()
+        );
+
+        invoked.lock().unwrap().clear();
+        drop(first);
+        mock.key_up(Keyboard::J);
+        mock.key_down(Keyboard::J);
+        assert_eq!(vec![("second", Keyboard::J)], *invoked.lock().unwrap());
+
+        invoked.lock().unwrap().clear();
+        context.remove_any_key_bind();
+        mock.key_up(Keyboard::J);
+        mock.key_down(Keyboard::J);
+        assert!(invoked.lock().unwrap().is_empty());
+    }
+}
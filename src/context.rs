@@ -0,0 +1,738 @@
+use crate::bind_handle::BindTarget;
+use crate::details::Registry;
+use crate::gesture::Recognizer;
+use crate::{
+    Action, Backend, BindHandle, Chords, Event, Gesture, GestureOptions, Hotkey, InhibitEvent,
+    Keyboard, LayerMode, Layout, Leader, Listener, Mouse, Recorder, BASE_LAYER,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::thread::JoinHandle;
+
+struct Running {
+    listening: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// Owns everything the library needs to operate: the binds, hotkeys, state, the sequencer thread,
+/// the backend and the `mki-lstn` listener thread.
+/// The free functions of this crate operate on the default context, see `context`.
+/// Multiple contexts can live next to each other, useful for tests with the `MockBackend`.
+/// Context stops listening when dropped.
+/// ```
+/// use mki::*;
+///
+/// fn isolated() {
+///   let context = Context::builder().backend(MockBackend::new()).build();
+///   context.start();
+///   let _handle = context.bind_key(Keyboard::B, Action::handle_kb(|_| println!("B Pressed")));
+///   context.shutdown();
+/// }
+/// ```
+pub struct Context {
+    registry: Arc<Registry>,
+    layout: RwLock<Arc<Layout>>,
+    running: Mutex<Option<Running>>,
+    started: AtomicBool,
+}
+
+/// Builder for the `Context`, nothing is started until `Context::start` is called.
+#[derive(Default)]
+pub struct ContextBuilder {
+    backend: Option<Arc<dyn Backend>>,
+    layout: Layout,
+    debug: bool,
+}
+
+impl ContextBuilder {
+    /// Backend to use, defaults to the platform one.
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Layout used to type text, US by default.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Same as `Context::enable_debug`.
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn build(self) -> Context {
+        let registry = Registry::new(self.backend.unwrap_or_else(platform_backend));
+        if self.debug {
+            registry.enable_debug();
+        }
+        Context {
+            registry: Arc::new(registry),
+            layout: RwLock::new(Arc::new(self.layout)),
+            running: Mutex::new(None),
+            started: AtomicBool::new(false),
+        }
+    }
+}
+
+fn platform_backend() -> Arc<dyn Backend> {
+    #[cfg(target_os = "linux")]
+    return Arc::new(crate::LinuxBackend::default());
+    #[cfg(target_os = "windows")]
+    return Arc::new(crate::WindowsBackend::default());
+}
+
+fn layer_target(layer: &str, target: BindTarget) -> BindTarget {
+    if layer == BASE_LAYER {
+        target
+    } else {
+        BindTarget::Layer(layer.into(), Box::new(target))
+    }
+}
+
+/// The default context used by all the free functions of this crate.
+/// It starts listening the first time anything is bound or queried.
+pub fn context() -> &'static Context {
+    lazy_static::lazy_static! {
+        static ref CONTEXT: Context = Context::new();
+    }
+    &CONTEXT
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Context {
+    /// Context with the platform backend.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::default()
+    }
+
+    /// Starts the listener thread, does nothing if already listening.
+    pub fn start(&self) {
+        self.started.store(true, Ordering::Relaxed);
+        let mut running = self.running.lock().unwrap();
+        if running.is_some() {
+            return;
+        }
+        let listening = Arc::new(AtomicBool::new(true));
+        let listener = Listener::new(self.registry.clone(), listening.clone());
+        let backend = self.backend();
+        let handle = thread::Builder::new()
+            .name("mki-lstn".into())
+            .spawn(move || backend.listen(listener))
+            .unwrap();
+        *running = Some(Running { listening, handle });
+    }
+
+    /// Stops the listener thread and waits for it to finish.
+    /// Binds and state are kept, the context can be started again.
+    pub fn shutdown(&self) {
+        let running = self.running.lock().unwrap().take();
+        if let Some(running) = running {
+            running.listening.store(false, Ordering::Relaxed);
+            self.backend().stop();
+            // Shutdown may be requested from a callback invoked on the listener thread.
+            if running.handle.thread().id() != thread::current().id() {
+                let _ = running.handle.join();
+            }
+        }
+    }
+
+    /// Whether the listener thread is running.
+    pub fn is_running(&self) -> bool {
+        self.running.lock().unwrap().is_some()
+    }
+
+    /// Starts listening unless this context was started before, used by the free functions.
+    pub(crate) fn autostart(&self) {
+        if !self.started.load(Ordering::Relaxed) {
+            self.start();
+        }
+    }
+
+    /// Backend used for the injection.
+    pub fn backend(&self) -> Arc<dyn Backend> {
+        self.registry.backend()
+    }
+
+    /// Replaces the backend, if context is listening it will be restarted with the new backend.
+    pub fn set_backend(&self, backend: impl Backend + 'static) {
+        let was_running = self.is_running();
+        self.shutdown();
+        self.registry.set_backend(Arc::new(backend));
+        if was_running {
+            self.start();
+        }
+    }
+
+    /// See `layout`.
+    pub fn layout(&self) -> Arc<Layout> {
+        self.layout.read().unwrap().clone()
+    }
+
+    /// See `set_layout`.
+    pub fn set_layout(&self, layout: Layout) {
+        *self.layout.write().unwrap() = Arc::new(layout);
+    }
+
+    /// See `bind_any_key`.
+    pub fn bind_any_key(&self, action: Action) -> BindHandle {
+        let id = self.registry.bind_any_key(action);
+        BindHandle::new(&self.registry, BindTarget::AnyKey, id)
+    }
+
+    /// See `bind_key`.
+    pub fn bind_key(&self, key: Keyboard, action: Action) -> BindHandle {
+        let id = self.registry.bind_key(key, action);
+        BindHandle::new(&self.registry, BindTarget::Key(key), id)
+    }
+
+    /// See `remove_any_key_bind`.
+    pub fn remove_any_key_bind(&self) {
+        self.registry.any_key_callbacks.lock().unwrap().clear();
+    }
+
+    /// See `remove_key_bind`.
+    pub fn remove_key_bind(&self, key: Keyboard) {
+        self.registry.key_callbacks.lock().unwrap().remove(&key);
+    }
+
+    /// See `bind_any_button`.
+    pub fn bind_any_button(&self, action: Action) -> BindHandle {
+        let id = self.registry.bind_any_button(action);
+        BindHandle::new(&self.registry, BindTarget::AnyButton, id)
+    }
+
+    /// See `bind_button`.
+    pub fn bind_button(&self, button: Mouse, action: Action) -> BindHandle {
+        let id = self.registry.bind_button(button, action);
+        BindHandle::new(&self.registry, BindTarget::Button(button), id)
+    }
+
+    /// See `bind_scroll`.
+    pub fn bind_scroll(
+        &self,
+        inhibit: InhibitEvent,
+        handler: impl Fn(i32, i32) + Send + Sync + 'static,
+    ) -> BindHandle {
+        let id = self
+            .registry
+            .bind_scroll(Arc::new(Box::new(handler)), inhibit);
+        BindHandle::new(&self.registry, BindTarget::Scroll, id)
+    }
+
+    /// See `bind_gesture`.
+    pub fn bind_gesture(
+        &self,
+        event: Event,
+        options: GestureOptions,
+        handler: impl Fn(Event, Gesture) + Send + Sync + 'static,
+    ) -> BindHandle {
+        let id = self
+            .registry
+            .bind_gesture(event, Recognizer::new(options, handler));
+        BindHandle::new(&self.registry, BindTarget::Gesture(event), id)
+    }
+
+    /// See `remove_any_button_bind`.
+    pub fn remove_any_button_bind(&self) {
+        self.registry.any_button_callbacks.lock().unwrap().clear();
+    }
+
+    /// See `remove_button_bind`.
+    pub fn remove_button_bind(&self, button: Mouse) {
+        self.registry
+            .button_callbacks
+            .lock()
+            .unwrap()
+            .remove(&button);
+    }
+
+    /// See `register_hotkey`.
+    pub fn register_hotkey(
+        &self,
+        hotkey: impl Into<Hotkey>,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> BindHandle {
+        let id = self.registry.register_hotkey(hotkey.into(), callback);
+        BindHandle::new(&self.registry, BindTarget::Hotkey, id)
+    }
+
+    /// See `register_chords`.
+    pub fn register_chords(
+        &self,
+        chords: Chords,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> BindHandle {
+        let id = self.registry.register_chords(chords, callback);
+        BindHandle::new(&self.registry, BindTarget::Chords, id)
+    }
+
+    /// See `register_leader`.
+    pub fn register_leader(
+        &self,
+        leader: Leader,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> BindHandle {
+        let id = self.registry.register_leader(leader, callback);
+        BindHandle::new(&self.registry, BindTarget::Leader, id)
+    }
+
+    /// See `layer_key`.
+    pub fn layer_key(&self, key: Keyboard, layer: &str, mode: LayerMode) -> BindHandle {
+        let id = self.registry.layer_key(key, layer, mode);
+        BindHandle::new(&self.registry, BindTarget::Key(key), id)
+    }
+
+    /// See `bind_key_in`.
+    pub fn bind_key_in(&self, layer: &str, key: Keyboard, action: Action) -> BindHandle {
+        let id = self.registry.bind_key_in(layer, key, action);
+        BindHandle::new(
+            &self.registry,
+            layer_target(layer, BindTarget::Key(key)),
+            id,
+        )
+    }
+
+    /// See `bind_button_in`.
+    pub fn bind_button_in(&self, layer: &str, button: Mouse, action: Action) -> BindHandle {
+        let id = self.registry.bind_button_in(layer, button, action);
+        BindHandle::new(
+            &self.registry,
+            layer_target(layer, BindTarget::Button(button)),
+            id,
+        )
+    }
+
+    /// See `register_hotkey_in`.
+    pub fn register_hotkey_in(
+        &self,
+        layer: &str,
+        hotkey: impl Into<Hotkey>,
+        handler: impl Fn() + Send + Sync + 'static,
+    ) -> BindHandle {
+        let id = self
+            .registry
+            .register_hotkey_in(layer, hotkey.into(), handler);
+        BindHandle::new(&self.registry, layer_target(layer, BindTarget::Hotkey), id)
+    }
+
+    /// See `push_layer`.
+    pub fn push_layer(&self, layer: &str) {
+        self.registry.push_layer(layer)
+    }
+
+    /// See `pop_layer`.
+    pub fn pop_layer(&self, layer: &str) {
+        self.registry.pop_layer(layer)
+    }
+
+    /// See `toggle_layer`.
+    pub fn toggle_layer(&self, layer: &str) {
+        self.registry.toggle_layer(layer)
+    }
+
+    /// See `switch_layer`.
+    pub fn switch_layer(&self, layer: &str) {
+        self.registry.switch_layer(layer)
+    }
+
+    /// See `active_layer`.
+    pub fn active_layer(&self) -> String {
+        self.registry.active_layer()
+    }
+
+    /// See `active_layers`.
+    pub fn active_layers(&self) -> Vec<String> {
+        self.registry.active_layers()
+    }
+
+    /// See `pending_chord`.
+    pub fn pending_chord(&self) -> Vec<Hotkey> {
+        self.registry.pending_chord()
+    }
+
+    /// See `on_chord_aborted`.
+    pub fn on_chord_aborted(&self, f: impl Fn(Vec<Hotkey>) + Send + Sync + 'static) {
+        self.registry.on_chord_aborted(Some(Arc::new(Box::new(f))));
+    }
+
+    /// See `remap`.
+    pub fn remap(&self, from: Keyboard, to: Keyboard) -> BindHandle {
+        self.remap_combo(&[from], &[to])
+    }
+
+    /// See `remap_combo`.
+    pub fn remap_combo(&self, from: &[Keyboard], to: &[Keyboard]) -> BindHandle {
+        let id = self.registry.remap(from, to);
+        BindHandle::new(&self.registry, BindTarget::Remap, id)
+    }
+
+    /// Starts recording the events observed by this context, see `Recorder`.
+    pub fn record(&self) -> Recorder {
+        Recorder::new(self.registry.clone())
+    }
+
+    /// See `unregister_hotkey`.
+    pub fn unregister_hotkey(&self, hotkey: impl Into<Hotkey>) {
+        self.registry.unregister_hotkey(&hotkey.into());
+    }
+
+    /// See `are_pressed`.
+    pub fn are_pressed(&self, sequence: &[Keyboard]) -> bool {
+        self.registry.are_pressed(sequence)
+    }
+
+    /// Whether given key or button is pressed.
+    pub fn is_pressed(&self, event: Event) -> bool {
+        self.registry.is_pressed(event)
+    }
+
+    /// See `Mouse::track`.
+    pub fn track_mouse(&self, f: impl Fn(i32, i32) + Send + Sync + 'static) {
+        self.registry.set_mouse_tracker(Some(Arc::new(Box::new(f))));
+    }
+
+    /// See `Mouse::track_scroll`.
+    pub fn track_scroll(&self, f: impl Fn(i32, i32) + Send + Sync + 'static) {
+        self.registry
+            .set_scroll_tracker(Some(Arc::new(Box::new(f))));
+    }
+
+    /// See `set_state`.
+    pub fn set_state(&self, key: &str, value: &str) {
+        self.registry.set_state(key, value)
+    }
+
+    /// See `get_state`.
+    pub fn get_state(&self, key: &str) -> Option<String> {
+        self.registry.get_state(key)
+    }
+
+    pub fn enable_debug(&self) {
+        self.registry.enable_debug();
+    }
+
+    pub fn print_pressed_state(&self) {
+        self.registry.print_pressed_state();
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Action, Context, InhibitEvent, Injected, Keyboard, MockBackend};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn lifecycle() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        let invoked = Arc::new(AtomicUsize::new(0));
+        let _bind = context.bind_key(Keyboard::A, {
+            let invoked = invoked.clone();
+            Action::callback_kb(move |_| {
+                invoked.fetch_add(1, Ordering::Relaxed);
+            })
+        });
+        assert!(!context.is_running());
+
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        assert!(context.is_running());
+        mock.key_down(Keyboard::A);
+        mock.key_up(Keyboard::A);
+        assert_eq!(1, invoked.load(Ordering::Relaxed));
+
+        context.shutdown();
+        assert!(!context.is_running());
+
+        // Binds survive the restart.
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        mock.key_down(Keyboard::A);
+        mock.key_up(Keyboard::A);
+        assert_eq!(2, invoked.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn contexts_are_independent() {
+        let first = MockBackend::new();
+        let first_context = Context::builder().backend(first.clone()).build();
+        let second = MockBackend::new();
+        let second_context = Context::builder().backend(second.clone()).build();
+        first_context.start();
+        assert!(first.wait_for_listener(Duration::from_secs(1)));
+        second_context.start();
+        assert!(second.wait_for_listener(Duration::from_secs(1)));
+
+        first_context.set_state("mode", "first");
+        assert_eq!(None, second_context.get_state("mode"));
+
+        first.key_down(Keyboard::Q);
+        assert!(first_context.are_pressed(&[Keyboard::Q]));
+        assert!(!second_context.are_pressed(&[Keyboard::Q]));
+    }
+
+    #[test]
+    fn remap() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let _caps = context.remap(Keyboard::CapsLock, Keyboard::Escape);
+        let _backspace = context.remap_combo(
+            &[Keyboard::LeftControl, Keyboard::H],
+            &[Keyboard::BackSpace],
+        );
+        // Injection happens on the sequencer thread.
+        let injected = |count: usize| {
+            for _ in 0..100 {
+                if mock.injected().len() >= count {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            let injected = mock.injected();
+            mock.clear();
+            injected
+        };
+
+        assert!(matches!(
+            mock.key_down(Keyboard::CapsLock),
+            InhibitEvent::Yes
+        ));
+        mock.key_down(Keyboard::CapsLock);
+        assert!(matches!(mock.key_up(Keyboard::CapsLock), InhibitEvent::Yes));
+        assert_eq!(
+            vec![
+                Injected::Press(Keyboard::Escape),
+                Injected::Press(Keyboard::Escape),
+                Injected::Release(Keyboard::Escape),
+            ],
+            injected(3)
+        );
+        assert!(!context.is_pressed(crate::Event::Keyboard(Keyboard::CapsLock)));
+
+        assert!(matches!(
+            mock.key_down(Keyboard::LeftControl),
+            InhibitEvent::No
+        ));
+        mock.key_down(Keyboard::H);
+        mock.key_up(Keyboard::H);
+        mock.key_up(Keyboard::LeftControl);
+        assert_eq!(
+            vec![
+                Injected::Release(Keyboard::LeftControl),
+                Injected::Press(Keyboard::BackSpace),
+                Injected::Release(Keyboard::BackSpace),
+                Injected::Press(Keyboard::LeftControl),
+            ],
+            injected(4)
+        );
+
+        // Without the modifier H is left alone.
+        assert!(matches!(mock.key_down(Keyboard::H), InhibitEvent::No));
+        mock.key_up(Keyboard::H);
+        assert!(injected(0).is_empty());
+    }
+
+    #[test]
+    fn chords() {
+        use crate::{Chords, Hotkey};
+        use std::sync::Mutex;
+        use Keyboard::*;
+
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let fired = Arc::new(AtomicUsize::new(0));
+        let _chords = context.register_chords(
+            "Ctrl+K Ctrl+C"
+                .parse::<Chords>()
+                .unwrap()
+                .timeout(Duration::from_millis(200)),
+            {
+                let fired = fired.clone();
+                move || {
+                    fired.fetch_add(1, Ordering::Relaxed);
+                }
+            },
+        );
+        let aborted = Arc::new(Mutex::new(Vec::new()));
+        context.on_chord_aborted({
+            let aborted = aborted.clone();
+            move |strokes| aborted.lock().unwrap().push(strokes)
+        });
+        let stroke = |keys: &[Keyboard]| {
+            for key in keys {
+                mock.key_down(*key);
+            }
+            for key in keys.iter().rev() {
+                mock.key_up(*key);
+            }
+        };
+        let wait = || thread::sleep(Duration::from_millis(50));
+
+        stroke(&[LeftControl, K]);
+        assert_eq!(
+            vec!["Ctrl+K".parse::<Hotkey>().unwrap()],
+            context.pending_chord()
+        );
+        stroke(&[LeftControl, X]);
+        assert!(context.pending_chord().is_empty());
+        wait();
+        assert_eq!(0, fired.load(Ordering::Relaxed));
+        assert_eq!(1, aborted.lock().unwrap().len());
+
+        stroke(&[LeftControl, K]);
+        stroke(&[RightControl, C]);
+        wait();
+        assert_eq!(1, fired.load(Ordering::Relaxed));
+
+        stroke(&[LeftControl, K]);
+        thread::sleep(Duration::from_millis(300));
+        assert!(context.pending_chord().is_empty());
+        assert_eq!(2, aborted.lock().unwrap().len());
+        stroke(&[LeftControl, C]);
+        wait();
+        assert_eq!(1, fired.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn leader() {
+        use crate::Leader;
+        use Keyboard::*;
+
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let fired = Arc::new(AtomicUsize::new(0));
+        let _leader = context.register_leader(
+            Leader::new(CapsLock, &[G, S])
+                .suppress(true)
+                .timeout(Duration::from_millis(200)),
+            {
+                let fired = fired.clone();
+                move || {
+                    fired.fetch_add(1, Ordering::Relaxed);
+                }
+            },
+        );
+        let typed = Arc::new(AtomicUsize::new(0));
+        let _bind = context.bind_key(G, {
+            let typed = typed.clone();
+            Action::callback_kb(move |_| {
+                typed.fetch_add(1, Ordering::Relaxed);
+            })
+        });
+        let tap = |key| {
+            let inhibit = mock.key_down(key);
+            mock.key_up(key);
+            inhibit
+        };
+
+        assert!(tap(CapsLock).should_inhibit());
+        assert!(tap(G).should_inhibit());
+        assert!(tap(S).should_inhibit());
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(1, fired.load(Ordering::Relaxed));
+        assert_eq!(0, typed.load(Ordering::Relaxed));
+
+        // Without the leader the keys pass through.
+        assert!(!tap(G).should_inhibit());
+        assert_eq!(1, typed.load(Ordering::Relaxed));
+
+        tap(CapsLock);
+        thread::sleep(Duration::from_millis(300));
+        assert!(!tap(G).should_inhibit());
+        tap(S);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(1, fired.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn layers() {
+        use crate::{LayerMode, BASE_LAYER};
+        use Keyboard::*;
+
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let _nav = context.layer_key(CapsLock, "nav", LayerMode::Momentary);
+        let _num = context.layer_key(Tab, "num", LayerMode::Toggle);
+        let counter = |name: &'static str, typed: &Arc<Mutex<Vec<&'static str>>>| {
+            let typed = typed.clone();
+            Action::callback_kb(move |_| typed.lock().unwrap().push(name))
+        };
+        let typed = Arc::new(Mutex::new(Vec::new()));
+        let _base_h = context.bind_key(H, counter("base h", &typed));
+        let _base_j = context.bind_key(J, counter("base j", &typed));
+        let _nav_h = context.bind_key_in("nav", H, counter("nav h", &typed));
+        let _num_h = context.bind_key_in("num", H, counter("num h", &typed));
+        let _hotkey = context.register_hotkey_in("nav", &[LeftShift, K], {
+            let typed = typed.clone();
+            move || typed.lock().unwrap().push("nav shift k")
+        });
+        let tap = |key| {
+            mock.key_down(key);
+            mock.key_up(key);
+        };
+
+        tap(H);
+        assert!(mock.key_down(CapsLock).should_inhibit());
+        mock.key_down(CapsLock);
+        assert_eq!("nav", context.active_layer());
+        tap(H);
+        // Not bound in the layer, falls through to the base one.
+        tap(J);
+        mock.key_down(LeftShift);
+        tap(K);
+        mock.key_up(LeftShift);
+        thread::sleep(Duration::from_millis(50));
+        mock.key_up(CapsLock);
+        assert_eq!(BASE_LAYER, context.active_layer());
+
+        tap(Tab);
+        mock.key_down(CapsLock);
+        assert_eq!(vec!["nav", "num", BASE_LAYER], context.active_layers());
+        mock.key_up(CapsLock);
+        tap(H);
+        tap(Tab);
+        tap(H);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(
+            vec![
+                "base h",
+                "nav h",
+                "base j",
+                "nav shift k",
+                "num h",
+                "base h"
+            ],
+            *typed.lock().unwrap()
+        );
+    }
+}
@@ -1,18 +1,24 @@
-use crate::{install_hooks, process_message, Action, Event, Mouse, State};
+use crate::bind_handle::BindTarget;
+use crate::context::{context, Context};
+use crate::gesture::Recognizer;
+use crate::hotkey::is_modifier;
+use crate::layer::{layer_action, LayerBinds, LayerMode, LayerStack, BASE_LAYER};
+use crate::recorder::Recorded;
+use crate::{Action, Backend, Chords, Event, Hotkey, Leader, Mouse, State};
 use crate::{InhibitEvent, Keyboard};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
-pub(crate) fn registry() -> &'static Registry {
-    lazy_static::lazy_static! {
-        static ref REGISTRY: Registry = Registry::new();
-    }
-    &REGISTRY
+/// The default context, starts listening upon first usage.
+pub(crate) fn started_context() -> &'static Context {
+    let context = context();
+    context.autostart();
+    context
 }
 
 struct Sequencer {
@@ -63,56 +69,166 @@ impl Pressed {
     }
 }
 
+pub(crate) type HotkeyCallback = Arc<Box<dyn Fn() + Send + Sync + 'static>>;
+
+type ChordBind = (Arc<Chords>, HotkeyCallback);
+
+pub(crate) type ScrollCallback = Arc<Box<dyn Fn(i32, i32) + Send + Sync + 'static>>;
+
+pub(crate) type ChordAbortCallback = Arc<Box<dyn Fn(Vec<Hotkey>) + Send + Sync + 'static>>;
+
+/// Strokes of registered chords matched so far.
+struct PendingChords {
+    strokes: Vec<Hotkey>,
+    /// Ids of the chords starting with `strokes`.
+    candidates: Vec<u64>,
+    since: Instant,
+}
+
+/// Shared with the thread aborting the pending chords after their timeout.
+#[derive(Default)]
+struct ChordState {
+    pending: Option<PendingChords>,
+    /// Incremented on every stroke, tells the timeout thread whether it is still relevant.
+    generation: u64,
+    on_abort: Option<ChordAbortCallback>,
+}
+
+impl ChordState {
+    /// Drops the pending chords, returns the callback to invoke with the aborted strokes.
+    fn abort(&mut self) -> Option<(ChordAbortCallback, Vec<Hotkey>)> {
+        self.generation += 1;
+        let pending = self.pending.take()?;
+        self.on_abort
+            .clone()
+            .map(|on_abort| (on_abort, pending.strokes))
+    }
+}
+
+type LeaderBind = (Arc<Leader>, HotkeyCallback);
+
+/// Leader key was pressed and the following keys are being typed.
+struct ActiveLeader {
+    typed: Vec<Keyboard>,
+    /// Ids of the leader binds whose keys start with `typed`.
+    candidates: Vec<u64>,
+    since: Instant,
+}
+
+#[derive(Default)]
+struct LeaderState {
+    active: Option<ActiveLeader>,
+    /// Keys whose press was inhibited, so is their release.
+    suppressed: HashSet<Keyboard>,
+}
+
+pub(crate) type Recorder = Arc<Box<dyn Fn(Recorded) + Send + Sync + 'static>>;
+
+/// Every bind is identified so that it can be removed by its handle,
+/// ids are increasing hence also define the order of invocation.
+pub(crate) type Binds<T> = Vec<(u64, T)>;
+
+/// Keys emitted instead of the `from` combination, see `remap_combo`.
+pub(crate) struct Remap {
+    pub(crate) from: Vec<Keyboard>,
+    pub(crate) to: Vec<Keyboard>,
+}
+
+impl Remap {
+    fn modifiers(&self) -> &[Keyboard] {
+        &self.from[..self.from.len() - 1]
+    }
+}
+
+/// Remap whose triggering key is held down.
+struct ActiveRemap {
+    remap: Arc<Remap>,
+    /// Modifiers of the combination that were released for the duration of the remap,
+    /// these are pressed back afterwards unless the user released them meanwhile.
+    released: Vec<Keyboard>,
+}
+
 pub(crate) struct Registry {
-    pub(crate) key_callbacks: Mutex<HashMap<Keyboard, Arc<Action>>>,
-    pub(crate) button_callbacks: Mutex<HashMap<Mouse, Arc<Action>>>,
-    pub(crate) any_key_callback: Mutex<Option<Arc<Action>>>,
-    pub(crate) any_button_callback: Mutex<Option<Arc<Action>>>,
-    #[allow(clippy::type_complexity)]
-    pub(crate) hotkeys: Mutex<HashMap<Vec<Keyboard>, Arc<Box<dyn Fn() + Send + Sync + 'static>>>>,
+    backend: RwLock<Arc<dyn Backend>>,
+    pub(crate) key_callbacks: Mutex<HashMap<Keyboard, Binds<Arc<Action>>>>,
+    pub(crate) button_callbacks: Mutex<HashMap<Mouse, Binds<Arc<Action>>>>,
+    pub(crate) any_key_callbacks: Mutex<Binds<Arc<Action>>>,
+    pub(crate) any_button_callbacks: Mutex<Binds<Arc<Action>>>,
+    scroll_callbacks: Mutex<Binds<(ScrollCallback, InhibitEvent)>>,
+    pub(crate) hotkeys: Mutex<Binds<(Hotkey, HotkeyCallback)>>,
+    layers: Mutex<HashMap<String, LayerBinds>>,
+    layer_stack: Arc<Mutex<LayerStack>>,
+    recorders: Mutex<Binds<Recorder>>,
+    chords: Mutex<Binds<ChordBind>>,
+    chord_state: Arc<Mutex<ChordState>>,
+    leaders: Mutex<Binds<LeaderBind>>,
+    leader_state: Mutex<LeaderState>,
+    remaps: Mutex<Binds<Arc<Remap>>>,
+    active_remaps: Mutex<HashMap<Keyboard, ActiveRemap>>,
+    next_bind_id: AtomicU64,
     #[allow(clippy::type_complexity)]
     mouse_tracking_callback: Mutex<Option<Arc<Box<dyn Fn(i32, i32) + Send + Sync + 'static>>>>,
+    #[allow(clippy::type_complexity)]
+    scroll_tracking_callback: Mutex<Option<Arc<Box<dyn Fn(i32, i32) + Send + Sync + 'static>>>>,
 
     pressed: Mutex<Pressed>,
 
-    _handle: JoinHandle<()>,
     sequencer: Mutex<Option<Sequencer>>,
 
     state: Mutex<HashMap<String, String>>,
 
     pub(crate) tracking_enabled: AtomicBool,
+    recording: AtomicBool,
     pub(crate) debug_enabled: AtomicBool,
 }
 
 impl Registry {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(backend: Arc<dyn Backend>) -> Self {
         Registry {
+            backend: RwLock::new(backend),
             key_callbacks: Mutex::new(HashMap::new()),
             button_callbacks: Mutex::new(HashMap::new()),
-            any_key_callback: Mutex::new(None),
-            any_button_callback: Mutex::new(None),
-            _handle: thread::Builder::new()
-                .name("mki-lstn".into())
-                .spawn(|| {
-                    // For windows hooks need to be installed on the same thread that listens to the Messages.
-                    install_hooks();
-                    process_message();
-                })
-                .unwrap(),
+            any_key_callbacks: Mutex::new(Vec::new()),
+            any_button_callbacks: Mutex::new(Vec::new()),
+            scroll_callbacks: Mutex::new(Vec::new()),
             sequencer: Mutex::new(None),
             pressed: Mutex::new(Pressed::default()),
-            hotkeys: Mutex::new(HashMap::new()),
+            hotkeys: Mutex::new(Vec::new()),
+            layers: Mutex::new(HashMap::new()),
+            layer_stack: Arc::new(Mutex::new(LayerStack::default())),
+            recorders: Mutex::new(Vec::new()),
+            chords: Mutex::new(Vec::new()),
+            chord_state: Arc::new(Mutex::new(ChordState::default())),
+            leaders: Mutex::new(Vec::new()),
+            leader_state: Mutex::new(LeaderState::default()),
+            remaps: Mutex::new(Vec::new()),
+            active_remaps: Mutex::new(HashMap::new()),
+            next_bind_id: AtomicU64::new(0),
             state: Mutex::new(HashMap::new()),
             tracking_enabled: AtomicBool::new(false),
+            recording: AtomicBool::new(false),
             mouse_tracking_callback: Mutex::new(None),
+            scroll_tracking_callback: Mutex::new(None),
             debug_enabled: AtomicBool::new(false),
         }
     }
 
+    pub(crate) fn backend(&self) -> Arc<dyn Backend> {
+        self.backend.read().unwrap().clone()
+    }
+
+    pub(crate) fn set_backend(&self, backend: Arc<dyn Backend>) {
+        *self.backend.write().unwrap() = backend;
+    }
+
     pub(crate) fn sequence(&self, event: Event, state: State, action: Arc<Action>) {
-        let erased_action = Box::new(move || {
+        self.enqueue(Box::new(move || {
             (action.callback)(event, state);
-        });
+        }));
+    }
+
+    /// Runs given function on the sequencer thread, after everything enqueued before.
+    fn enqueue(&self, erased_action: Box<dyn Fn() + Send + Sync>) {
         let mut sequencer = self.sequencer.lock().unwrap();
         let sequencer = sequencer.get_or_insert({
             let (tx, rx) = mpsc::channel::<Box<dyn Fn() + Send + Sync>>();
@@ -146,68 +262,333 @@ impl Registry {
         }
     }
 
-    fn map_event_to_actions(&self, event: Event) -> (Option<Arc<Action>>, Option<Arc<Action>>) {
-        let (global_action, key_action) = match event {
+    fn map_event_to_actions(&self, event: Event) -> (Vec<Arc<Action>>, Vec<Arc<Action>>) {
+        fn all(binds: &Binds<Arc<Action>>) -> Vec<Arc<Action>> {
+            binds.iter().map(|(_, action)| action.clone()).collect()
+        }
+        fn actions<K: Eq + std::hash::Hash>(
+            binds: &HashMap<K, Binds<Arc<Action>>>,
+            key: &K,
+        ) -> Vec<Arc<Action>> {
+            binds.get(key).map(all).unwrap_or_default()
+        }
+        // Layers are searched top-down, the first one binding the event wins.
+        let layered = {
+            let layers = self.layers.lock().unwrap();
+            let active = self.layer_stack.lock().unwrap().active();
+            active.iter().find_map(|layer| {
+                let binds = layers.get(layer)?;
+                let actions = match event {
+                    Event::Keyboard(key) => actions(&binds.keys, &key),
+                    Event::Mouse(button) => actions(&binds.buttons, &button),
+                };
+                Some(actions).filter(|actions| !actions.is_empty())
+            })
+        };
+        let (global_action, key_actions) = match event {
             Event::Keyboard(key) => (
-                self.any_key_callback.lock().unwrap().clone(),
-                self.key_callbacks.lock().unwrap().get(&key).cloned(),
+                all(&self.any_key_callbacks.lock().unwrap()),
+                layered.unwrap_or_else(|| actions(&self.key_callbacks.lock().unwrap(), &key)),
             ),
             Event::Mouse(button) => (
-                self.any_button_callback.lock().unwrap().clone(),
-                self.button_callbacks.lock().unwrap().get(&button).cloned(),
+                all(&self.any_button_callbacks.lock().unwrap()),
+                layered.unwrap_or_else(|| actions(&self.button_callbacks.lock().unwrap(), &button)),
             ),
         };
-        (global_action, key_action)
+        (global_action, key_actions)
+    }
+
+    /// Callbacks of the hotkeys matching in the topmost layer having any.
+    fn matching_hotkeys(&self, pressed: &[Keyboard], key: Keyboard) -> Vec<HotkeyCallback> {
+        let held = self.layer_stack.lock().unwrap().held.clone();
+        let pressed: Vec<Keyboard> = pressed
+            .iter()
+            .copied()
+            .filter(|pressed| !held.contains(pressed))
+            .collect();
+        let pressed = pressed.as_slice();
+        let matching = |hotkeys: &Binds<(Hotkey, HotkeyCallback)>| -> Vec<HotkeyCallback> {
+            hotkeys
+                .iter()
+                .filter(|(_, (hotkey, _))| hotkey.matches(pressed, key))
+                .map(|(_, (_, callback))| callback.clone())
+                .collect()
+        };
+        let layered = {
+            let layers = self.layers.lock().unwrap();
+            let active = self.layer_stack.lock().unwrap().active();
+            active.iter().find_map(|layer| {
+                let binds = layers.get(layer)?;
+                Some(matching(&binds.hotkeys)).filter(|callbacks| !callbacks.is_empty())
+            })
+        };
+        layered.unwrap_or_else(|| matching(&self.hotkeys.lock().unwrap()))
+    }
+
+    pub(crate) fn push_layer(&self, layer: &str) {
+        self.layer_stack.lock().unwrap().push(layer)
+    }
+
+    pub(crate) fn pop_layer(&self, layer: &str) {
+        self.layer_stack.lock().unwrap().pop(layer)
+    }
+
+    pub(crate) fn toggle_layer(&self, layer: &str) {
+        self.layer_stack.lock().unwrap().toggle(layer)
+    }
+
+    pub(crate) fn switch_layer(&self, layer: &str) {
+        self.layer_stack.lock().unwrap().switch(layer)
+    }
+
+    pub(crate) fn active_layer(&self) -> String {
+        self.layer_stack.lock().unwrap().top().to_string()
     }
 
+    pub(crate) fn active_layers(&self) -> Vec<String> {
+        self.layer_stack.lock().unwrap().active()
+    }
+
+    /// Binds the key to activate the layer, the bind itself belongs to the base layer.
+    pub(crate) fn layer_key(&self, key: Keyboard, layer: &str, mode: LayerMode) -> u64 {
+        self.bind_key(key, layer_action(self.layer_stack.clone(), layer, mode))
+    }
+
+    pub(crate) fn bind_key_in(&self, layer: &str, key: Keyboard, action: Action) -> u64 {
+        if layer == BASE_LAYER {
+            return self.bind_key(key, action);
+        }
+        let id = self.next_bind_id();
+        self.layers
+            .lock()
+            .unwrap()
+            .entry(layer.into())
+            .or_default()
+            .keys
+            .entry(key)
+            .or_default()
+            .push((id, Arc::new(action)));
+        id
+    }
+
+    pub(crate) fn bind_button_in(&self, layer: &str, button: Mouse, action: Action) -> u64 {
+        if layer == BASE_LAYER {
+            return self.bind_button(button, action);
+        }
+        let id = self.next_bind_id();
+        self.layers
+            .lock()
+            .unwrap()
+            .entry(layer.into())
+            .or_default()
+            .buttons
+            .entry(button)
+            .or_default()
+            .push((id, Arc::new(action)));
+        id
+    }
+
+    pub(crate) fn register_hotkey_in(
+        &self,
+        layer: &str,
+        hotkey: Hotkey,
+        handler: impl Fn() + Send + Sync + 'static,
+    ) -> u64 {
+        if layer == BASE_LAYER {
+            return self.register_hotkey(hotkey, handler);
+        }
+        let id = self.next_bind_id();
+        self.layers
+            .lock()
+            .unwrap()
+            .entry(layer.into())
+            .or_default()
+            .hotkeys
+            .push((id, (hotkey, Arc::new(Box::new(handler)))));
+        id
+    }
+
+    /// Event observed on a device, remaps are applied before anything else.
+    /// Keys suppressed by leader binds do not reach the binds.
     pub(crate) fn event_down(&self, event: Event) -> InhibitEvent {
+        if let Event::Keyboard(key) = event {
+            if self.remap_down(key) || self.leader_down(key) {
+                return InhibitEvent::Yes;
+            }
+        }
+        self.dispatch_down(event)
+    }
+
+    /// Event observed on a device.
+    pub(crate) fn event_up(&self, event: Event) -> InhibitEvent {
+        if let Event::Keyboard(key) = event {
+            if self.remap_up(key) || self.leader_state.lock().unwrap().suppressed.remove(&key) {
+                return InhibitEvent::Yes;
+            }
+        }
+        self.dispatch_up(event)
+    }
+
+    /// Tracks the keys typed after a leader key, returns whether the key is suppressed.
+    fn leader_down(&self, key: Keyboard) -> bool {
+        let leaders = self.leaders.lock().unwrap();
+        if leaders.is_empty() {
+            return false;
+        }
+        let mut state = self.leader_state.lock().unwrap();
+        if state.suppressed.contains(&key) {
+            // Repeat of a suppressed key.
+            return true;
+        }
+        if self
+            .pressed
+            .lock()
+            .unwrap()
+            .is_pressed(Event::Keyboard(key))
+        {
+            return false;
+        }
+        let leader_bind = |id: &u64| leaders.iter().find(|(bind_id, _)| bind_id == id);
+        if let Some(mut active) = state.active.take() {
+            let elapsed = active.since.elapsed();
+            active.candidates.retain(|id| {
+                leader_bind(id).is_some_and(|(_, (leader, _))| elapsed <= leader.timeout_duration())
+            });
+            if !active.candidates.is_empty() {
+                if is_modifier(key) {
+                    state.active = Some(active);
+                    return false;
+                }
+                let suppress = active
+                    .candidates
+                    .iter()
+                    .filter_map(leader_bind)
+                    .any(|(_, (leader, _))| leader.suppresses());
+                active.typed.push(key);
+                let typed = active.typed.clone();
+                active.candidates.retain(|id| {
+                    leader_bind(id)
+                        .is_some_and(|(_, (leader, _))| leader.keys().starts_with(&typed))
+                });
+                let completed: Vec<HotkeyCallback> = active
+                    .candidates
+                    .iter()
+                    .filter_map(leader_bind)
+                    .filter(|(_, (leader, _))| leader.keys() == typed.as_slice())
+                    .map(|(_, (_, callback))| callback.clone())
+                    .collect();
+                if !completed.is_empty() {
+                    thread::spawn(move || {
+                        for callback in completed {
+                            callback()
+                        }
+                    });
+                } else if !active.candidates.is_empty() {
+                    active.since = Instant::now();
+                    state.active = Some(active);
+                }
+                if suppress {
+                    state.suppressed.insert(key);
+                }
+                return suppress;
+            }
+        }
+        let candidates: Vec<&(u64, LeaderBind)> = leaders
+            .iter()
+            .filter(|(_, (leader, _))| leader.leader() == key)
+            .collect();
+        if candidates.is_empty() {
+            return false;
+        }
+        let suppress = candidates
+            .iter()
+            .any(|(_, (leader, _))| leader.suppresses());
+        state.active = Some(ActiveLeader {
+            typed: Vec::new(),
+            candidates: candidates.iter().map(|(id, _)| *id).collect(),
+            since: Instant::now(),
+        });
+        if suppress {
+            state.suppressed.insert(key);
+        }
+        suppress
+    }
+
+    pub(crate) fn register_leader(
+        &self,
+        leader: Leader,
+        handler: impl Fn() + Send + Sync + 'static,
+    ) -> u64 {
+        let id = self.next_bind_id();
+        self.leaders
+            .lock()
+            .unwrap()
+            .push((id, (Arc::new(leader), Arc::new(Box::new(handler)))));
+        id
+    }
+
+    /// Passes the event to the binds and hotkeys, remaps do not apply to injected events.
+    pub(crate) fn dispatch_down(&self, event: Event) -> InhibitEvent {
         self.maybe_log_event("down", event);
-        self.pressed.lock().unwrap().pressed(event);
+        let repeated = {
+            let mut pressed = self.pressed.lock().unwrap();
+            let repeated = pressed.is_pressed(event);
+            pressed.pressed(event);
+            repeated
+        };
+        self.record(match event {
+            Event::Keyboard(key) => Some(Recorded::KeyDown(key)),
+            Event::Mouse(button) if button.scroll_delta().is_none() => {
+                Some(Recorded::ButtonDown(button))
+            }
+            Event::Mouse(_) => None,
+        });
         let mut callbacks = Vec::new();
         if let Event::Keyboard(key) = event {
-            for (sequence, callback) in self.hotkeys.lock().unwrap().iter() {
-                if sequence.last() == Some(&key)
-                    && self.pressed.lock().unwrap().are_pressed(sequence)
-                {
-                    callbacks.push(callback.clone());
-                }
+            let pressed = self.pressed.lock().unwrap().pressed_keys.clone();
+            callbacks.extend(self.matching_hotkeys(&pressed, key));
+            if !repeated {
+                callbacks.extend(self.chord_down(&pressed, key));
             }
         }
-        for callback in callbacks {
+        if !callbacks.is_empty() {
             // Should we not invoke actions if there is any hotkey present?
-            thread::spawn(move || callback());
+            thread::spawn(move || {
+                for callback in callbacks {
+                    callback()
+                }
+            });
         }
         let state = State::Pressed;
-        let mut inhibit = InhibitEvent::No;
-        let (global_action, key_action) = self.map_event_to_actions(event);
-        if let Some(action) = global_action {
-            inhibit = action.inhibit.clone();
-            self.invoke_action(action, event, state);
-        }
-        if let Some(action) = key_action {
-            inhibit = action.inhibit.clone();
+        let mut inhibits = Vec::new();
+        let (global_actions, key_actions) = self.map_event_to_actions(event);
+        for action in global_actions.into_iter().chain(key_actions) {
+            inhibits.push(action.inhibit.clone());
             self.invoke_action(action, event, state);
         }
 
-        inhibit
+        InhibitEvent::any(inhibits)
     }
 
-    pub(crate) fn event_up(&self, event: Event) -> InhibitEvent {
+    pub(crate) fn dispatch_up(&self, event: Event) -> InhibitEvent {
         self.maybe_log_event("up", event);
         self.pressed.lock().unwrap().released(event);
+        self.record(match event {
+            Event::Keyboard(key) => Some(Recorded::KeyUp(key)),
+            Event::Mouse(button) if button.scroll_delta().is_none() => {
+                Some(Recorded::ButtonUp(button))
+            }
+            Event::Mouse(_) => None,
+        });
         let state = State::Released;
-        let (global_action, key_action) = self.map_event_to_actions(event);
-        if let Some(action) = global_action {
-            self.invoke_action(action, event, state);
-        }
-        if let Some(action) = key_action {
+        let (global_actions, key_actions) = self.map_event_to_actions(event);
+        for action in global_actions.into_iter().chain(key_actions) {
             self.invoke_action(action, event, state);
         }
 
         InhibitEvent::No
     }
 
-    #[cfg(target_os = "windows")] // Not sure how to detect double on linux
     pub(crate) fn event_click(&self, event: Event) -> InhibitEvent {
         self.maybe_log_event("click", event);
         let inhibit = self.event_down(event);
@@ -215,6 +596,224 @@ impl Registry {
         inhibit
     }
 
+    /// Wheel moved by given clicks, scroll binds get the clicks as they are unless `injected`,
+    /// then every click is reported as click of the matching Scroll* button.
+    pub(crate) fn event_scroll(&self, dx: i32, dy: i32, injected: bool) -> InhibitEvent {
+        self.record(Some(Recorded::Scroll(dx, dy)));
+        if let Some(scroll_tracking) = self.scroll_tracking_callback.lock().unwrap().clone() {
+            scroll_tracking(dx, dy);
+        }
+        let mut inhibits = Vec::new();
+        if !injected {
+            for (_, (callback, inhibit)) in self.scroll_callbacks.lock().unwrap().iter() {
+                let callback = callback.clone();
+                thread::spawn(move || callback(dx, dy));
+                inhibits.push(inhibit.clone());
+            }
+        }
+        let horizontal = if dx > 0 {
+            Mouse::ScrollRight
+        } else {
+            Mouse::ScrollLeft
+        };
+        let vertical = if dy > 0 {
+            Mouse::ScrollUp
+        } else {
+            Mouse::ScrollDown
+        };
+        for (button, clicks) in [(horizontal, dx), (vertical, dy)].iter() {
+            for _ in 0..clicks.abs() {
+                inhibits.push(self.event_click(Event::Mouse(*button)));
+            }
+        }
+        InhibitEvent::any(inhibits)
+    }
+
+    /// Advances the pending chords, returns the callbacks of the completed ones.
+    fn chord_down(&self, pressed: &[Keyboard], key: Keyboard) -> Vec<HotkeyCallback> {
+        let chords = self.chords.lock().unwrap();
+        if chords.is_empty() {
+            return Vec::new();
+        }
+        let mut state = self.chord_state.lock().unwrap();
+        let (matched, candidates): (usize, Vec<&(u64, ChordBind)>) = match &state.pending {
+            Some(pending) => {
+                let elapsed = pending.since.elapsed();
+                let matched = pending.strokes.len();
+                let candidates = chords
+                    .iter()
+                    .filter(|(id, (chords, _))| {
+                        pending.candidates.contains(id)
+                            && elapsed <= chords.timeout_duration()
+                            && chords.strokes()[matched].matches(pressed, key)
+                    })
+                    .collect();
+                (matched, candidates)
+            }
+            None => {
+                let candidates = chords
+                    .iter()
+                    .filter(|(_, (chords, _))| chords.strokes()[0].matches(pressed, key))
+                    .collect();
+                (0, candidates)
+            }
+        };
+        if candidates.is_empty() {
+            if state.pending.is_some() && !is_modifier(key) {
+                if let Some((on_abort, strokes)) = state.abort() {
+                    thread::spawn(move || on_abort(strokes));
+                }
+            }
+            return Vec::new();
+        }
+        let completed: Vec<HotkeyCallback> = candidates
+            .iter()
+            .filter(|(_, (chords, _))| chords.strokes().len() == matched + 1)
+            .map(|(_, (_, callback))| callback.clone())
+            .collect();
+        state.generation += 1;
+        if !completed.is_empty() {
+            // Complete chords win over the longer ones sharing the prefix.
+            state.pending = None;
+            return completed;
+        }
+        let (_, (first, _)) = candidates[0];
+        let timeout = candidates
+            .iter()
+            .map(|(_, (chords, _))| chords.timeout_duration())
+            .max()
+            .unwrap_or_default();
+        state.pending = Some(PendingChords {
+            strokes: first.strokes()[..=matched].to_vec(),
+            candidates: candidates.iter().map(|(id, _)| *id).collect(),
+            since: Instant::now(),
+        });
+        let generation = state.generation;
+        let chord_state = self.chord_state.clone();
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            let mut state = chord_state.lock().unwrap();
+            if state.generation == generation {
+                let aborted = state.abort();
+                drop(state);
+                if let Some((on_abort, strokes)) = aborted {
+                    on_abort(strokes);
+                }
+            }
+        });
+        Vec::new()
+    }
+
+    pub(crate) fn register_chords(
+        &self,
+        chords: Chords,
+        handler: impl Fn() + Send + Sync + 'static,
+    ) -> u64 {
+        let id = self.next_bind_id();
+        self.chords
+            .lock()
+            .unwrap()
+            .push((id, (Arc::new(chords), Arc::new(Box::new(handler)))));
+        id
+    }
+
+    /// Strokes of the registered chords typed so far, empty unless some are pending.
+    pub(crate) fn pending_chord(&self) -> Vec<Hotkey> {
+        let state = self.chord_state.lock().unwrap();
+        state
+            .pending
+            .as_ref()
+            .map(|pending| pending.strokes.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn on_chord_aborted(&self, callback: Option<ChordAbortCallback>) {
+        self.chord_state.lock().unwrap().on_abort = callback;
+    }
+
+    /// Whether the key triggered a remap, repeated presses of an active remap emit its keys again.
+    fn remap_down(&self, key: Keyboard) -> bool {
+        let mut active_remaps = self.active_remaps.lock().unwrap();
+        let mut strokes = Vec::new();
+        let remap = if let Some(active) = active_remaps.get(&key) {
+            active.remap.clone()
+        } else {
+            // The most specific combination wins, the newest one among the equally specific.
+            let remap = self
+                .remaps
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, remap)| remap)
+                .filter(|remap| {
+                    remap.from.last() == Some(&key)
+                        && remap
+                            .modifiers()
+                            .iter()
+                            .all(|modifier| self.is_pressed(Event::Keyboard(*modifier)))
+                })
+                .max_by_key(|remap| remap.from.len())
+                .cloned();
+            let remap = match remap {
+                Some(remap) => remap,
+                None => return false,
+            };
+            let released: Vec<Keyboard> = remap
+                .modifiers()
+                .iter()
+                .filter(|modifier| !remap.to.contains(modifier))
+                .copied()
+                .collect();
+            strokes.extend(released.iter().map(|key| (*key, State::Released)));
+            active_remaps.insert(
+                key,
+                ActiveRemap {
+                    remap: remap.clone(),
+                    released,
+                },
+            );
+            remap
+        };
+        strokes.extend(remap.to.iter().map(|key| (*key, State::Pressed)));
+        self.inject(strokes);
+        true
+    }
+
+    /// Whether the key finished a remap, otherwise the key is forgotten by the active remaps.
+    fn remap_up(&self, key: Keyboard) -> bool {
+        let mut active_remaps = self.active_remaps.lock().unwrap();
+        if let Some(active) = active_remaps.remove(&key) {
+            let strokes = active
+                .remap
+                .to
+                .iter()
+                .rev()
+                .map(|key| (*key, State::Released))
+                .chain(active.released.iter().map(|key| (*key, State::Pressed)))
+                .collect();
+            self.inject(strokes);
+            true
+        } else {
+            for active in active_remaps.values_mut() {
+                active.released.retain(|released| *released != key);
+            }
+            false
+        }
+    }
+
+    /// Injection cannot happen on the listening thread, sequenced so that the order is kept.
+    fn inject(&self, strokes: Vec<(Keyboard, State)>) {
+        let backend = self.backend();
+        self.enqueue(Box::new(move || {
+            for (key, state) in strokes.iter() {
+                match state {
+                    State::Pressed => backend.press(*key),
+                    State::Released => backend.release(*key),
+                }
+            }
+        }));
+    }
+
     pub(crate) fn is_pressed(&self, event: Event) -> bool {
         self.pressed.lock().unwrap().is_pressed(event)
     }
@@ -223,34 +822,217 @@ impl Registry {
         self.pressed.lock().unwrap().are_pressed(keys)
     }
 
+    fn next_bind_id(&self) -> u64 {
+        self.next_bind_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub(crate) fn bind_any_key(&self, action: Action) -> u64 {
+        let id = self.next_bind_id();
+        self.any_key_callbacks
+            .lock()
+            .unwrap()
+            .push((id, Arc::new(action)));
+        id
+    }
+
+    pub(crate) fn bind_any_button(&self, action: Action) -> u64 {
+        let id = self.next_bind_id();
+        self.any_button_callbacks
+            .lock()
+            .unwrap()
+            .push((id, Arc::new(action)));
+        id
+    }
+
+    pub(crate) fn bind_scroll(&self, callback: ScrollCallback, inhibit: InhibitEvent) -> u64 {
+        let id = self.next_bind_id();
+        self.scroll_callbacks
+            .lock()
+            .unwrap()
+            .push((id, (callback, inhibit)));
+        id
+    }
+
+    /// Binds the recognizer to the event, and to every other one when it has to see those.
+    pub(crate) fn bind_gesture(&self, event: Event, recognizer: Arc<Recognizer>) -> u64 {
+        let id = self.next_bind_id();
+        let action = Arc::new(recognizer.action());
+        match event {
+            Event::Keyboard(key) => self
+                .key_callbacks
+                .lock()
+                .unwrap()
+                .entry(key)
+                .or_default()
+                .push((id, action)),
+            Event::Mouse(button) => self
+                .button_callbacks
+                .lock()
+                .unwrap()
+                .entry(button)
+                .or_default()
+                .push((id, action)),
+        }
+        if recognizer.watches_others() {
+            let interrupting = Arc::new(recognizer.interrupting_action());
+            self.any_key_callbacks
+                .lock()
+                .unwrap()
+                .push((id, interrupting.clone()));
+            self.any_button_callbacks
+                .lock()
+                .unwrap()
+                .push((id, interrupting));
+        }
+        id
+    }
+
+    pub(crate) fn bind_key(&self, key: Keyboard, action: Action) -> u64 {
+        let id = self.next_bind_id();
+        self.key_callbacks
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .push((id, Arc::new(action)));
+        id
+    }
+
+    pub(crate) fn bind_button(&self, button: Mouse, action: Action) -> u64 {
+        let id = self.next_bind_id();
+        self.button_callbacks
+            .lock()
+            .unwrap()
+            .entry(button)
+            .or_default()
+            .push((id, Arc::new(action)));
+        id
+    }
+
+    pub(crate) fn remap(&self, from: &[Keyboard], to: &[Keyboard]) -> u64 {
+        assert!(!from.is_empty(), "Cannot remap an empty combination");
+        let id = self.next_bind_id();
+        self.remaps.lock().unwrap().push((
+            id,
+            Arc::new(Remap {
+                from: from.to_vec(),
+                to: to.to_vec(),
+            }),
+        ));
+        id
+    }
+
     pub(crate) fn register_hotkey(
         &self,
-        sequence: &[Keyboard],
+        hotkey: Hotkey,
         handler: impl Fn() + Send + Sync + 'static,
-    ) {
-        let mut hotkeys = self.hotkeys.lock().unwrap();
-        if self.debug_enabled.load(Ordering::Relaxed) {
-            hotkeys.insert(
-                sequence.to_vec(),
-                Arc::new(Box::new({
-                    let sequence = sequence.to_vec();
-                    move || {
-                        println!(
-                            "Invoking hotkey. sequence: {:?} ts: {:?}",
-                            sequence,
-                            log_timestamp()
-                        );
-                        handler()
-                    }
-                })),
-            );
+    ) -> u64 {
+        let id = self.next_bind_id();
+        let callback: HotkeyCallback = if self.debug_enabled.load(Ordering::Relaxed) {
+            Arc::new(Box::new({
+                let hotkey = hotkey.clone();
+                move || {
+                    println!(
+                        "Invoking hotkey. hotkey: {:?} ts: {:?}",
+                        hotkey,
+                        log_timestamp()
+                    );
+                    handler()
+                }
+            }))
         } else {
-            hotkeys.insert(sequence.to_vec(), Arc::new(Box::new(handler)));
+            Arc::new(Box::new(handler))
         };
+        self.hotkeys.lock().unwrap().push((id, (hotkey, callback)));
+        id
     }
 
-    pub(crate) fn unregister_hotkey(&self, sequence: &[Keyboard]) {
-        self.hotkeys.lock().unwrap().remove(&sequence.to_vec());
+    pub(crate) fn unregister_hotkey(&self, hotkey: &Hotkey) {
+        self.hotkeys
+            .lock()
+            .unwrap()
+            .retain(|(_, (registered, _))| registered != hotkey);
+    }
+
+    /// Removes single bind, see `BindHandle`.
+    pub(crate) fn unbind(&self, target: &BindTarget, id: u64) {
+        fn remove<K: Eq + std::hash::Hash, T>(binds: &mut HashMap<K, Binds<T>>, key: &K, id: u64) {
+            if let Some(entries) = binds.get_mut(key) {
+                entries.retain(|(bind_id, _)| *bind_id != id);
+                if entries.is_empty() {
+                    binds.remove(key);
+                }
+            }
+        }
+        match target {
+            BindTarget::AnyKey => self
+                .any_key_callbacks
+                .lock()
+                .unwrap()
+                .retain(|(bind_id, _)| *bind_id != id),
+            BindTarget::AnyButton => self
+                .any_button_callbacks
+                .lock()
+                .unwrap()
+                .retain(|(bind_id, _)| *bind_id != id),
+            BindTarget::Scroll => self
+                .scroll_callbacks
+                .lock()
+                .unwrap()
+                .retain(|(bind_id, _)| *bind_id != id),
+            BindTarget::Gesture(event) => {
+                match event {
+                    Event::Keyboard(key) => {
+                        remove(&mut self.key_callbacks.lock().unwrap(), key, id)
+                    }
+                    Event::Mouse(button) => {
+                        remove(&mut self.button_callbacks.lock().unwrap(), button, id)
+                    }
+                }
+                self.any_key_callbacks
+                    .lock()
+                    .unwrap()
+                    .retain(|(bind_id, _)| *bind_id != id);
+                self.any_button_callbacks
+                    .lock()
+                    .unwrap()
+                    .retain(|(bind_id, _)| *bind_id != id);
+            }
+            BindTarget::Key(key) => remove(&mut self.key_callbacks.lock().unwrap(), key, id),
+            BindTarget::Button(button) => {
+                remove(&mut self.button_callbacks.lock().unwrap(), button, id)
+            }
+            BindTarget::Hotkey => self
+                .hotkeys
+                .lock()
+                .unwrap()
+                .retain(|(hotkey_id, _)| *hotkey_id != id),
+            BindTarget::Layer(layer, target) => {
+                if let Some(binds) = self.layers.lock().unwrap().get_mut(layer) {
+                    match target.as_ref() {
+                        BindTarget::Key(key) => remove(&mut binds.keys, key, id),
+                        BindTarget::Button(button) => remove(&mut binds.buttons, button, id),
+                        _ => binds.hotkeys.retain(|(hotkey_id, _)| *hotkey_id != id),
+                    }
+                }
+            }
+            BindTarget::Leader => self
+                .leaders
+                .lock()
+                .unwrap()
+                .retain(|(leader_id, _)| *leader_id != id),
+            BindTarget::Chords => self
+                .chords
+                .lock()
+                .unwrap()
+                .retain(|(chords_id, _)| *chords_id != id),
+            // Active remap is still finished once its key is released.
+            BindTarget::Remap => self
+                .remaps
+                .lock()
+                .unwrap()
+                .retain(|(remap_id, _)| *remap_id != id),
+        }
     }
 
     //noinspection ALL
@@ -263,7 +1045,32 @@ impl Registry {
     }
 
     pub fn is_tracking_enabled(&self) -> bool {
-        self.tracking_enabled.load(Ordering::Relaxed)
+        self.tracking_enabled.load(Ordering::Relaxed) || self.recording.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn add_recorder(&self, recorder: Recorder) -> u64 {
+        let id = self.next_bind_id();
+        let mut recorders = self.recorders.lock().unwrap();
+        recorders.push((id, recorder));
+        self.recording.store(true, Ordering::Relaxed);
+        id
+    }
+
+    pub(crate) fn remove_recorder(&self, id: u64) {
+        let mut recorders = self.recorders.lock().unwrap();
+        recorders.retain(|(recorder_id, _)| *recorder_id != id);
+        self.recording
+            .store(!recorders.is_empty(), Ordering::Relaxed);
+    }
+
+    fn record(&self, event: Option<Recorded>) {
+        if let Some(event) = event {
+            if self.recording.load(Ordering::Relaxed) {
+                for (_, recorder) in self.recorders.lock().unwrap().iter() {
+                    recorder(event)
+                }
+            }
+        }
     }
 
     #[allow(clippy::type_complexity)]
@@ -276,9 +1083,18 @@ impl Registry {
         *self.mouse_tracking_callback.lock().unwrap() = action;
     }
 
+    #[allow(clippy::type_complexity)]
+    pub fn set_scroll_tracker(
+        &self,
+        action: Option<Arc<Box<dyn Fn(i32, i32) + Send + Sync + 'static>>>,
+    ) {
+        *self.scroll_tracking_callback.lock().unwrap() = action;
+    }
+
     #[allow(unused)]
     pub(crate) fn update_mouse_position(&self, x: i32, y: i32) {
-        if self.is_tracking_enabled() {
+        self.record(Some(Recorded::MoveTo(x, y)));
+        if self.tracking_enabled.load(Ordering::Relaxed) {
             if let Some(mouse_tracking) = self.mouse_tracking_callback.lock().unwrap().as_ref() {
                 mouse_tracking(x, y)
             }
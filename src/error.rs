@@ -0,0 +1,31 @@
+use crate::Keyboard;
+use std::fmt;
+
+/// Why injecting an event failed, returned by `Keyboard::try_press` and the like.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Not allowed to open the input devices, usually requires root or the `input` group.
+    PermissionDenied(String),
+    /// Injection device is missing or stopped working, e.g. the uinput module is not loaded.
+    DeviceUnavailable(String),
+    /// Cannot connect to the X server, e.g. `DISPLAY` is not set.
+    NoDisplay,
+    /// Key has no code the backend could inject.
+    UnmappedKey(Keyboard),
+    /// Backend cannot do this at all, e.g. inject `Mouse::Forward` on Windows.
+    Unsupported(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PermissionDenied(what) => write!(f, "Permission denied: {}", what),
+            Error::DeviceUnavailable(what) => write!(f, "Device unavailable: {}", what),
+            Error::NoDisplay => f.write_str("Cannot open the X display"),
+            Error::UnmappedKey(key) => write!(f, "Key {} cannot be injected", key),
+            Error::Unsupported(what) => write!(f, "Unsupported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
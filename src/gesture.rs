@@ -0,0 +1,430 @@
+use crate::{Action, Event, InhibitEvent, State};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Higher level interpretation of the presses and releases of a key or a button.
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+pub enum Gesture {
+    /// Released within the `tap` threshold.
+    Tap,
+    /// Released after the `tap` threshold or after `LongPress`, carries for how long it was held.
+    Hold(Duration),
+    /// Second tap pressed within the `double_tap` threshold after the first one, reported after its `Tap`
+    /// unless `GestureOptions::wait_for_double_tap` reports it alone.
+    DoubleTap,
+    /// Still held after the `long_press` threshold, or once another key or button is pressed
+    /// with `GestureOptions::hold_on_other_key`, reported while held.
+    LongPress,
+}
+
+/// Thresholds used to tell the gestures apart, see `Action::gesture`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct GestureThresholds {
+    pub tap: Duration,
+    pub double_tap: Duration,
+    pub long_press: Duration,
+}
+
+impl Default for GestureThresholds {
+    fn default() -> Self {
+        GestureThresholds {
+            tap: Duration::from_millis(200),
+            double_tap: Duration::from_millis(300),
+            long_press: Duration::from_millis(500),
+        }
+    }
+}
+
+/// How the gestures of a key or a button are told apart, see `bind_gesture`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct GestureOptions {
+    thresholds: GestureThresholds,
+    hold_on_other_key: bool,
+    wait_for_double_tap: bool,
+    inhibit: bool,
+}
+
+impl GestureOptions {
+    pub fn thresholds(mut self, thresholds: GestureThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Pressing another key or button while held reports `LongPress` right away,
+    /// before that press is passed on, and the release reports `Hold` however short it was.
+    pub fn hold_on_other_key(mut self, hold_on_other_key: bool) -> Self {
+        self.hold_on_other_key = hold_on_other_key;
+        self
+    }
+
+    /// Reports a `Tap` only once the `double_tap` threshold passes without a second one,
+    /// or once another key or button is pressed, so a double tap is reported as a lone `DoubleTap`.
+    pub fn wait_for_double_tap(mut self, wait_for_double_tap: bool) -> Self {
+        self.wait_for_double_tap = wait_for_double_tap;
+        self
+    }
+
+    /// Inhibits the presses of the bound key or button, on Linux see `LinuxBackend::grab`.
+    pub fn inhibit(mut self, inhibit: bool) -> Self {
+        self.inhibit = inhibit;
+        self
+    }
+}
+
+#[derive(Default)]
+struct Tracker {
+    pressed_at: Option<Instant>,
+    /// Incremented on every press and release, tells the pending threads whether they are still relevant.
+    generation: u64,
+    last_tap: Option<Instant>,
+    double_tap: bool,
+    /// `LongPress` was reported for the current press.
+    resolved: bool,
+}
+
+impl Tracker {
+    /// Tap held back by `wait_for_double_tap` until it is known not to start a double tap.
+    fn pending_tap(&mut self, options: &GestureOptions) -> Option<Gesture> {
+        if options.wait_for_double_tap && (self.double_tap || self.last_tap.is_some()) {
+            self.double_tap = false;
+            self.last_tap = None;
+            Some(Gesture::Tap)
+        } else {
+            None
+        }
+    }
+}
+
+type GestureHandler = Arc<dyn Fn(Event, Gesture) + Send + Sync + 'static>;
+
+/// Gestures of every key or button it is fed with, shared by the actions of a `bind_gesture`.
+pub(crate) struct Recognizer {
+    options: GestureOptions,
+    action: GestureHandler,
+    trackers: Mutex<BTreeMap<Event, Tracker>>,
+}
+
+impl Recognizer {
+    pub(crate) fn new(
+        options: GestureOptions,
+        action: impl Fn(Event, Gesture) + Send + Sync + 'static,
+    ) -> Arc<Self> {
+        Arc::new(Recognizer {
+            options,
+            action: Arc::new(action),
+            trackers: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Action feeding the presses and releases of the keys or buttons it is bound to.
+    pub(crate) fn action(self: &Arc<Self>) -> Action {
+        let recognizer = self.clone();
+        Action {
+            callback: Box::new(move |event, state| match state {
+                State::Pressed => recognizer.pressed(event),
+                State::Released => recognizer.released(event),
+            }),
+            inhibit: if self.options.inhibit {
+                InhibitEvent::Yes
+            } else {
+                InhibitEvent::No
+            },
+            defer: false,
+            sequencer: false,
+        }
+    }
+
+    /// Whether the recognizer has to see the presses of the other keys and buttons.
+    pub(crate) fn watches_others(&self) -> bool {
+        self.options.hold_on_other_key || self.options.wait_for_double_tap
+    }
+
+    /// Action feeding the presses of every key or button, resolving the pending gestures of the other ones.
+    pub(crate) fn interrupting_action(self: &Arc<Self>) -> Action {
+        let recognizer = self.clone();
+        Action::callback(move |event| recognizer.interrupted(event))
+    }
+
+    fn report(&self, event: Event, gestures: Vec<Gesture>) {
+        if !gestures.is_empty() {
+            let action = self.action.clone();
+            thread::spawn(move || {
+                for gesture in gestures {
+                    action(event, gesture);
+                }
+            });
+        }
+    }
+
+    fn pressed(self: &Arc<Self>, event: Event) {
+        let mut all = self.trackers.lock().unwrap();
+        let tracker = all.entry(event).or_default();
+        if tracker.pressed_at.is_some() {
+            // Repeat.
+            return;
+        }
+        let now = Instant::now();
+        let thresholds = self.options.thresholds;
+        tracker.pressed_at = Some(now);
+        tracker.generation += 1;
+        tracker.resolved = false;
+        tracker.double_tap = tracker
+            .last_tap
+            .is_some_and(|last| now - last <= thresholds.double_tap);
+        // Tap whose double tap threshold passed before its thread reported it.
+        let late_tap = if tracker.double_tap {
+            None
+        } else {
+            let late_tap = tracker.pending_tap(&self.options);
+            tracker.last_tap = None;
+            late_tap
+        };
+        let generation = tracker.generation;
+        let recognizer = self.clone();
+        thread::spawn(move || {
+            thread::sleep(thresholds.long_press);
+            let mut all = recognizer.trackers.lock().unwrap();
+            let tracker = match all.get_mut(&event) {
+                Some(tracker) => tracker,
+                None => return,
+            };
+            if tracker.generation == generation && !tracker.resolved {
+                tracker.resolved = true;
+                let gestures = tracker
+                    .pending_tap(&recognizer.options)
+                    .into_iter()
+                    .chain(Some(Gesture::LongPress))
+                    .collect();
+                drop(all);
+                recognizer.report(event, gestures);
+            }
+        });
+        drop(all);
+        self.report(event, late_tap.into_iter().collect());
+    }
+
+    fn released(self: &Arc<Self>, event: Event) {
+        let mut all = self.trackers.lock().unwrap();
+        let tracker = all.entry(event).or_default();
+        let now = Instant::now();
+        let held = match tracker.pressed_at.take() {
+            Some(pressed_at) => now - pressed_at,
+            None => return,
+        };
+        let options = self.options;
+        tracker.generation += 1;
+        let mut gestures = Vec::new();
+        if tracker.resolved || held > options.thresholds.tap {
+            gestures.extend(tracker.pending_tap(&options));
+            gestures.push(Gesture::Hold(held));
+            tracker.last_tap = None;
+        } else if tracker.double_tap {
+            if !options.wait_for_double_tap {
+                gestures.push(Gesture::Tap);
+            }
+            gestures.push(Gesture::DoubleTap);
+            tracker.double_tap = false;
+            tracker.last_tap = None;
+        } else {
+            tracker.last_tap = Some(now);
+            if options.wait_for_double_tap {
+                let generation = tracker.generation;
+                let recognizer = self.clone();
+                thread::spawn(move || {
+                    thread::sleep(options.thresholds.double_tap);
+                    let mut all = recognizer.trackers.lock().unwrap();
+                    let tap = all
+                        .get_mut(&event)
+                        .filter(|tracker| tracker.generation == generation)
+                        .and_then(|tracker| tracker.pending_tap(&recognizer.options));
+                    drop(all);
+                    recognizer.report(event, tap.into_iter().collect());
+                });
+            } else {
+                gestures.push(Gesture::Tap);
+            }
+        }
+        drop(all);
+        self.report(event, gestures);
+    }
+
+    /// Another key or button was pressed, the gestures it resolves are reported before it is passed on.
+    fn interrupted(&self, by: Event) {
+        let mut resolved = Vec::new();
+        for (event, tracker) in self.trackers.lock().unwrap().iter_mut() {
+            if *event == by {
+                continue;
+            }
+            let mut gestures: Vec<Gesture> =
+                tracker.pending_tap(&self.options).into_iter().collect();
+            if self.options.hold_on_other_key && tracker.pressed_at.is_some() && !tracker.resolved {
+                tracker.resolved = true;
+                gestures.push(Gesture::LongPress);
+            }
+            if !gestures.is_empty() {
+                tracker.generation += 1;
+                resolved.push((*event, gestures));
+            }
+        }
+        for (event, gestures) in resolved {
+            for gesture in gestures {
+                (self.action)(event, gesture);
+            }
+        }
+    }
+}
+
+impl Action {
+    /// Invokes the handler with the gestures of the bound keys or buttons, with the default thresholds.
+    /// Handler is spawned in new thread, the event is not inhibited.
+    /// See `bind_gesture` for resolving a hold once another key is pressed and the other options.
+    /// ```
+    /// use mki::*;
+    ///
+    /// fn caps_lock_gestures() {
+    ///   Keyboard::CapsLock.act_on(Action::gesture(|_, gesture| match gesture {
+    ///     Gesture::Tap => println!("Tapped"),
+    ///     Gesture::Hold(duration) => println!("Held for {:?}", duration),
+    ///     Gesture::DoubleTap => println!("Double tapped"),
+    ///     Gesture::LongPress => println!("Still held"),
+    ///   })).forget();
+    /// }
+    /// ```
+    pub fn gesture(action: impl Fn(Event, Gesture) + Send + Sync + 'static) -> Self {
+        Self::gesture_with(GestureThresholds::default(), action)
+    }
+
+    /// Version of `gesture` with custom thresholds.
+    pub fn gesture_with(
+        thresholds: GestureThresholds,
+        action: impl Fn(Event, Gesture) + Send + Sync + 'static,
+    ) -> Self {
+        Recognizer::new(GestureOptions::default().thresholds(thresholds), action).action()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Action, Context, Event, Gesture, GestureOptions, GestureThresholds, Keyboard, MockBackend,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    const THRESHOLDS: GestureThresholds = GestureThresholds {
+        tap: Duration::from_millis(50),
+        double_tap: Duration::from_millis(100),
+        long_press: Duration::from_millis(150),
+    };
+
+    #[test]
+    fn gestures() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let gestures = Arc::new(Mutex::new(Vec::new()));
+        let _bind = context.bind_key(
+            Keyboard::CapsLock,
+            Action::gesture_with(THRESHOLDS, {
+                let gestures = gestures.clone();
+                move |_, gesture| gestures.lock().unwrap().push(gesture)
+            }),
+        );
+        let hold = |millis| {
+            assert!(!mock.key_down(Keyboard::CapsLock).should_inhibit());
+            thread::sleep(Duration::from_millis(millis));
+            mock.key_up(Keyboard::CapsLock);
+            thread::sleep(Duration::from_millis(20));
+        };
+        let take = || std::mem::take(&mut *gestures.lock().unwrap());
+
+        hold(0);
+        assert_eq!(vec![Gesture::Tap], take());
+        hold(0);
+        assert_eq!(vec![Gesture::Tap, Gesture::DoubleTap], take());
+
+        thread::sleep(Duration::from_millis(150));
+        hold(0);
+        hold(80);
+        let gestures = take();
+        assert_eq!(Gesture::Tap, gestures[0]);
+        assert!(matches!(gestures[1], Gesture::Hold(held) if held >= Duration::from_millis(80)));
+
+        hold(200);
+        assert!(matches!(
+            take().as_slice(),
+            [Gesture::LongPress, Gesture::Hold(_)]
+        ));
+
+        // Other keys do not resolve the gestures of an action.
+        mock.key_down(Keyboard::CapsLock);
+        mock.key_down(Keyboard::A);
+        mock.key_up(Keyboard::A);
+        mock.key_up(Keyboard::CapsLock);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(vec![Gesture::Tap], take());
+    }
+
+    #[test]
+    fn hold_on_other_key() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let gestures = Arc::new(Mutex::new(Vec::new()));
+        let bind = context.bind_gesture(
+            Event::Keyboard(Keyboard::CapsLock),
+            GestureOptions::default()
+                .thresholds(THRESHOLDS)
+                .hold_on_other_key(true)
+                .wait_for_double_tap(true)
+                .inhibit(true),
+            {
+                let gestures = gestures.clone();
+                move |_, gesture| gestures.lock().unwrap().push(gesture)
+            },
+        );
+        let tap = || {
+            assert!(mock.key_down(Keyboard::CapsLock).should_inhibit());
+            mock.key_up(Keyboard::CapsLock);
+        };
+        let take = || std::mem::take(&mut *gestures.lock().unwrap());
+
+        // Resolved before the other key is passed on.
+        assert!(mock.key_down(Keyboard::CapsLock).should_inhibit());
+        assert!(!mock.key_down(Keyboard::A).should_inhibit());
+        assert_eq!(vec![Gesture::LongPress], take());
+        mock.key_up(Keyboard::A);
+        mock.key_up(Keyboard::CapsLock);
+        thread::sleep(Duration::from_millis(20));
+        assert!(matches!(take().as_slice(), [Gesture::Hold(_)]));
+
+        // Tap waits for the double tap threshold.
+        tap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(take().is_empty());
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(vec![Gesture::Tap], take());
+
+        tap();
+        tap();
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(vec![Gesture::DoubleTap], take());
+
+        // Other key ends the wait.
+        tap();
+        mock.key_down(Keyboard::A);
+        assert_eq!(vec![Gesture::Tap], take());
+        mock.key_up(Keyboard::A);
+
+        drop(bind);
+        assert!(!mock.key_down(Keyboard::CapsLock).should_inhibit());
+        mock.key_up(Keyboard::CapsLock);
+        thread::sleep(Duration::from_millis(150));
+        assert!(take().is_empty());
+    }
+}
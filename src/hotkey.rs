@@ -0,0 +1,509 @@
+use crate::Keyboard;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Key that has to be held for a `Hotkey`.
+/// `Ctrl`, `Shift`, `Alt` and `Super` match either side, `Key` matches only the given key.
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "ModifierRepr", into = "ModifierRepr")]
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Super,
+    Key(Keyboard),
+}
+
+impl Modifier {
+    pub fn matches(self, key: Keyboard) -> bool {
+        use Keyboard::*;
+        match self {
+            Modifier::Ctrl => key == LeftControl || key == RightControl,
+            Modifier::Shift => key == LeftShift || key == RightShift,
+            Modifier::Alt => key == LeftAlt || key == RightAlt,
+            Modifier::Super => key == LeftWindows || key == RightWindows,
+            Modifier::Key(modifier) => key == modifier,
+        }
+    }
+
+    /// Key to press for this modifier, the left one for those matching either side.
+    pub fn key(self) -> Keyboard {
+        match self {
+            Modifier::Ctrl => Keyboard::LeftControl,
+            Modifier::Shift => Keyboard::LeftShift,
+            Modifier::Alt => Keyboard::LeftAlt,
+            Modifier::Super => Keyboard::LeftWindows,
+            Modifier::Key(key) => key,
+        }
+    }
+}
+
+impl From<Keyboard> for Modifier {
+    fn from(key: Keyboard) -> Self {
+        Modifier::Key(key)
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "Ctrl" | "Control" => Modifier::Ctrl,
+            "Shift" => Modifier::Shift,
+            "Alt" => Modifier::Alt,
+            "Super" | "Win" | "Meta" => Modifier::Super,
+            key => Modifier::Key(Keyboard::from_str(key)?),
+        })
+    }
+}
+
+// In YAML the generic modifiers are written next to the keys, e.g. `[Ctrl, LeftShift, B]`.
+#[derive(Serialize, Deserialize)]
+enum Generic {
+    #[serde(alias = "Control")]
+    Ctrl,
+    Shift,
+    Alt,
+    #[serde(alias = "Win", alias = "Meta")]
+    Super,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ModifierRepr {
+    Generic(Generic),
+    Key(Keyboard),
+}
+
+impl From<ModifierRepr> for Modifier {
+    fn from(repr: ModifierRepr) -> Self {
+        match repr {
+            ModifierRepr::Generic(Generic::Ctrl) => Modifier::Ctrl,
+            ModifierRepr::Generic(Generic::Shift) => Modifier::Shift,
+            ModifierRepr::Generic(Generic::Alt) => Modifier::Alt,
+            ModifierRepr::Generic(Generic::Super) => Modifier::Super,
+            ModifierRepr::Key(key) => Modifier::Key(key),
+        }
+    }
+}
+
+impl From<Modifier> for ModifierRepr {
+    fn from(modifier: Modifier) -> Self {
+        match modifier {
+            Modifier::Ctrl => ModifierRepr::Generic(Generic::Ctrl),
+            Modifier::Shift => ModifierRepr::Generic(Generic::Shift),
+            Modifier::Alt => ModifierRepr::Generic(Generic::Alt),
+            Modifier::Super => ModifierRepr::Generic(Generic::Super),
+            Modifier::Key(key) => ModifierRepr::Key(key),
+        }
+    }
+}
+
+/// How the pressed keys are compared against a `Hotkey`.
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Matching {
+    /// Only the modifiers pressed in the given order followed by the key, as `are_pressed` does.
+    #[default]
+    Exact,
+    /// Only the modifiers and the key, pressed in any order.
+    /// Fires once the last of them goes down.
+    OrderInsensitive,
+    /// The modifiers and the key pressed in any order, other keys may be held as well.
+    AllowExtra,
+}
+
+/// Combination of modifiers and a trigger key, see `register_hotkey`.
+/// Keys convert into an exact hotkey where every key but the last is a modifier,
+/// an empty array does not compile while an empty slice or vector panics.
+/// ```
+/// use mki::*;
+///
+/// fn new_tab() {
+///   let hotkey = Hotkey::new(Keyboard::T)
+///     .modifier(Modifier::Ctrl)
+///     .modifier(Modifier::Shift)
+///     .matching(Matching::OrderInsensitive);
+///   assert_eq!(hotkey, "Ctrl+Shift+T".parse::<Hotkey>().unwrap().matching(Matching::OrderInsensitive));
+///   register_hotkey(hotkey, || println!("Ctrl+Shift+T pressed")).forget();
+/// }
+/// ```
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Hotkey {
+    modifiers: Vec<Modifier>,
+    key: Keyboard,
+    matching: Matching,
+}
+
+impl Hotkey {
+    pub fn new(key: Keyboard) -> Self {
+        Hotkey {
+            modifiers: Vec::new(),
+            key,
+            matching: Matching::default(),
+        }
+    }
+
+    /// Adds a modifier, in `Matching::Exact` the modifiers have to be pressed in the order they were added.
+    pub fn modifier(mut self, modifier: impl Into<Modifier>) -> Self {
+        self.modifiers.push(modifier.into());
+        self
+    }
+
+    pub fn matching(mut self, matching: Matching) -> Self {
+        self.matching = matching;
+        self
+    }
+
+    pub fn key(&self) -> Keyboard {
+        self.key
+    }
+
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+
+    /// Whether `key` going down while `pressed` are held triggers this hotkey,
+    /// `pressed` is in the order of pressing and includes `key`.
+    pub(crate) fn matches(&self, pressed: &[Keyboard], key: Keyboard) -> bool {
+        match self.matching {
+            Matching::Exact => {
+                key == self.key
+                    && pressed.len() == self.modifiers.len() + 1
+                    && pressed.last() == Some(&self.key)
+                    && self
+                        .modifiers
+                        .iter()
+                        .zip(pressed)
+                        .all(|(modifier, pressed)| modifier.matches(*pressed))
+            }
+            Matching::OrderInsensitive => {
+                pressed.len() == self.modifiers.len() + 1
+                    && self.involves(key)
+                    && self.is_held(pressed)
+            }
+            Matching::AllowExtra => self.involves(key) && self.is_held(pressed),
+        }
+    }
+
+    fn involves(&self, key: Keyboard) -> bool {
+        key == self.key || self.modifiers.iter().any(|modifier| modifier.matches(key))
+    }
+
+    /// Every modifier is matched by a different pressed key.
+    fn is_held(&self, pressed: &[Keyboard]) -> bool {
+        let mut available: Vec<Keyboard> = match pressed.iter().position(|key| *key == self.key) {
+            Some(index) => [&pressed[..index], &pressed[index + 1..]].concat(),
+            None => return false,
+        };
+        // Side specific modifiers first so that the generic ones do not take their keys.
+        let mut modifiers = self.modifiers.clone();
+        modifiers.sort_by_key(|modifier| !matches!(modifier, Modifier::Key(_)));
+        modifiers.iter().all(|modifier| {
+            match available.iter().position(|key| modifier.matches(*key)) {
+                Some(index) => {
+                    available.remove(index);
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+/// Parses modifiers and the key joined by `+`, such as `Ctrl+Shift+T` or `LeftAlt+F4`.
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let modifiers = s
+            .split('+')
+            .map(|part| Modifier::from_str(part.trim()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Unknown key in hotkey: {}", s))?;
+        Hotkey::try_from(modifiers.as_slice())
+    }
+}
+
+/// Last one has to be a key, the preceding ones are the modifiers.
+impl TryFrom<&[Modifier]> for Hotkey {
+    type Error = String;
+
+    fn try_from(keys: &[Modifier]) -> Result<Self, Self::Error> {
+        match keys.split_last() {
+            Some((Modifier::Key(key), modifiers)) => Ok(Hotkey {
+                modifiers: modifiers.to_vec(),
+                key: *key,
+                matching: Matching::default(),
+            }),
+            Some((modifier, _)) => Err(format!(
+                "Hotkey has to end with a key rather than {:?}",
+                modifier
+            )),
+            None => Err("Hotkey has no keys".into()),
+        }
+    }
+}
+
+impl From<Keyboard> for Hotkey {
+    fn from(key: Keyboard) -> Self {
+        Hotkey::new(key)
+    }
+}
+
+/// Last key is the trigger, the preceding ones are the modifiers.
+///
+/// # Panics
+///
+/// If `keys` is empty, `TryFrom<&[Modifier]>` reports it instead.
+impl From<&[Keyboard]> for Hotkey {
+    fn from(keys: &[Keyboard]) -> Self {
+        let keys: Vec<Modifier> = keys.iter().copied().map(Modifier::Key).collect();
+        Hotkey::try_from(keys.as_slice()).expect("Hotkey has no keys")
+    }
+}
+
+/// Fails to compile for an empty array.
+struct NonEmpty<const N: usize>;
+
+impl<const N: usize> NonEmpty<N> {
+    const CHECK: () = assert!(N > 0, "Hotkey has no keys");
+}
+
+impl<const N: usize> From<&[Keyboard; N]> for Hotkey {
+    fn from(keys: &[Keyboard; N]) -> Self {
+        let () = NonEmpty::<N>::CHECK;
+        Hotkey::from(&keys[..])
+    }
+}
+
+/// Same as the slice one, panics if `keys` is empty.
+impl From<&Vec<Keyboard>> for Hotkey {
+    fn from(keys: &Vec<Keyboard>) -> Self {
+        Hotkey::from(keys.as_slice())
+    }
+}
+
+/// Same as the slice one, panics if `keys` is empty.
+impl From<Vec<Keyboard>> for Hotkey {
+    fn from(keys: Vec<Keyboard>) -> Self {
+        Hotkey::from(keys.as_slice())
+    }
+}
+
+/// Successive hotkeys, such as `Ctrl+K Ctrl+C`, see `register_chords`.
+/// Every stroke has to follow the previous one within the timeout, 1 second by default.
+/// Presses of Ctrl, Shift, Alt and Super alone neither advance nor abort the pending strokes.
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Chords {
+    strokes: Vec<Hotkey>,
+    timeout: Duration,
+}
+
+impl Chords {
+    /// Hotkeys pressed one after another, see `from_str` for a fallible version.
+    ///
+    /// # Panics
+    ///
+    /// If `strokes` is empty.
+    pub fn new(strokes: impl IntoIterator<Item = impl Into<Hotkey>>) -> Self {
+        let strokes: Vec<Hotkey> = strokes.into_iter().map(Into::into).collect();
+        assert!(!strokes.is_empty(), "Chords have no strokes");
+        Chords {
+            strokes,
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Maximum time between two strokes.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn strokes(&self) -> &[Hotkey] {
+        &self.strokes
+    }
+
+    pub(crate) fn timeout_duration(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// Parses hotkeys separated by whitespace, such as `Ctrl+K Ctrl+C`.
+impl FromStr for Chords {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let strokes = s
+            .split_whitespace()
+            .map(Hotkey::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if strokes.is_empty() {
+            return Err("Chords have no strokes".into());
+        }
+        Ok(Chords::new(strokes))
+    }
+}
+
+/// Leader key followed by a short mnemonic, such as CapsLock then G and S, see `register_leader`.
+/// Every key has to follow the previous one within the timeout, 1 second by default.
+/// Presses of Ctrl, Shift, Alt and Super are ignored while typing the mnemonic.
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Leader {
+    leader: Keyboard,
+    keys: Vec<Keyboard>,
+    timeout: Duration,
+    suppress: bool,
+}
+
+impl Leader {
+    /// `leader` followed by `keys`, see `from_str` for a fallible version.
+    ///
+    /// # Panics
+    ///
+    /// If `keys` is empty.
+    pub fn new(leader: Keyboard, keys: &[Keyboard]) -> Self {
+        assert!(!keys.is_empty(), "Leader has no keys following it");
+        Leader {
+            leader,
+            keys: keys.to_vec(),
+            timeout: Duration::from_secs(1),
+            suppress: false,
+        }
+    }
+
+    /// Maximum time between two keys.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Inhibits the leader and the keys typed after it, requires event inhibition,
+    /// on Linux see `LinuxBackend::grab`.
+    pub fn suppress(mut self, suppress: bool) -> Self {
+        self.suppress = suppress;
+        self
+    }
+
+    pub fn leader(&self) -> Keyboard {
+        self.leader
+    }
+
+    pub fn keys(&self) -> &[Keyboard] {
+        &self.keys
+    }
+
+    pub(crate) fn timeout_duration(&self) -> Duration {
+        self.timeout
+    }
+
+    pub(crate) fn suppresses(&self) -> bool {
+        self.suppress
+    }
+}
+
+/// Parses the leader and the keys separated by whitespace, such as `CapsLock G S`.
+impl FromStr for Leader {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split_whitespace()
+            .map(|key| Keyboard::from_str(key).map_err(|_| format!("Unknown key in leader: {}", s)))
+            .collect::<Result<Vec<_>, _>>()?;
+        match keys.split_first() {
+            Some((leader, keys)) if !keys.is_empty() => Ok(Leader::new(*leader, keys)),
+            _ => Err(format!(
+                "Leader needs a leader and keys following it: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// Whether the key is one of Ctrl, Shift, Alt or Super.
+pub(crate) fn is_modifier(key: Keyboard) -> bool {
+    [
+        Modifier::Ctrl,
+        Modifier::Shift,
+        Modifier::Alt,
+        Modifier::Super,
+    ]
+    .iter()
+    .any(|modifier| modifier.matches(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Keyboard::*;
+    use crate::{Chords, Hotkey, Matching, Modifier};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn matching() {
+        let exact = Hotkey::from(&[LeftControl, B]);
+        assert_eq!(exact, Hotkey::from(vec![LeftControl, B]));
+        assert!(std::panic::catch_unwind(|| Hotkey::from(Vec::new())).is_err());
+        assert!(Hotkey::try_from(&[][..] as &[Modifier]).is_err());
+        assert!(exact.matches(&[LeftControl, B], B));
+        assert!(!exact.matches(&[RightControl, B], B));
+        assert!(!exact.matches(&[B, LeftControl], LeftControl));
+
+        let ctrl_b = Hotkey::new(B).modifier(Modifier::Ctrl);
+        assert!(ctrl_b.matches(&[RightControl, B], B));
+        assert!(!ctrl_b.matches(&[B, RightControl], RightControl));
+
+        let any_order = ctrl_b.clone().matching(Matching::OrderInsensitive);
+        assert!(any_order.matches(&[B, RightControl], RightControl));
+        assert!(!any_order.matches(&[LeftControl, A, B], B));
+
+        let extra = ctrl_b.matching(Matching::AllowExtra);
+        assert!(extra.matches(&[LeftControl, A, B], B));
+        assert!(!extra.matches(&[LeftControl, B, A], A));
+        assert!(!extra.matches(&[A, B], B));
+
+        let both_shifts = Hotkey::new(A)
+            .modifier(Modifier::Shift)
+            .modifier(LeftShift)
+            .matching(Matching::OrderInsensitive);
+        assert!(both_shifts.matches(&[LeftShift, RightShift, A], A));
+        assert!(!both_shifts.matches(&[LeftShift, A, LeftAlt], A));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Hotkey::new(T).modifier(Modifier::Ctrl).modifier(LeftShift),
+            "Ctrl+LeftShift+T".parse().unwrap()
+        );
+        assert!("Ctrl+Shift".parse::<Hotkey>().is_err());
+        let chords: Chords = "Ctrl+K  Ctrl+C".parse().unwrap();
+        assert_eq!(
+            &[
+                Hotkey::new(K).modifier(Modifier::Ctrl),
+                Hotkey::new(C).modifier(Modifier::Ctrl)
+            ],
+            chords.strokes()
+        );
+        assert!("Ctrl+Nope".parse::<Hotkey>().is_err());
+
+        let keys: Vec<Modifier> = serde_yaml::from_str("[Ctrl, Win, RightAlt, B]").unwrap();
+        assert_eq!(
+            vec![
+                Modifier::Ctrl,
+                Modifier::Super,
+                Modifier::Key(RightAlt),
+                Modifier::Key(B)
+            ],
+            keys
+        );
+        assert_eq!(
+            "---\n- Ctrl\n- LeftShift\n",
+            serde_yaml::to_string(&[Modifier::Ctrl, Modifier::Key(LeftShift)]).unwrap()
+        );
+    }
+}
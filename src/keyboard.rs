@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::Formatter;
 use std::str::FromStr;
 
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "KeyboardRepr", into = "KeyboardRepr")]
 pub enum Keyboard {
     A,
     B,
@@ -74,6 +76,8 @@ pub enum Keyboard {
     BackwardSlash, // \|
     RightBrace,    // ]}
     Grave,         // `~
+    Minus,         // -_
+    Equal,         // =+
     F1,
     F2,
     F3,
@@ -117,138 +121,361 @@ pub enum Keyboard {
     Subtract,
     Decimal,
     Divide,
+    NumpadEnter,
+    MediaPlayPause,
+    MediaNext,
+    MediaPrevious,
+    MediaStop,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    BrightnessDown,
+    BrightnessUp,
+    BrowserBack,
+    BrowserForward,
+    BrowserHome,
+    BrowserRefresh,
+    Calculator,
+    Mail,
     ThatThingy,
+    /// Platform code as reported before `Native` existed, injected like `Native`.
     Other(i32),
+    /// Set 1 scan code, the extended ones prefixed with 0xe0, e.g. `Scancode(0xe05f)`.
+    /// Injected as is on Windows, translated to the evdev code on Linux.
+    Scancode(u32),
+    /// Platform code, the evdev code on Linux and the virtual key code on Windows.
+    /// Reported for the keys without a variant.
+    Native(u32),
+}
+
+/// Names and codes of a key, see `KEYS`.
+pub(crate) struct KeyInfo {
+    pub(crate) key: Keyboard,
+    pub(crate) name: &'static str,
+    /// Also accepted by `from_str`.
+    pub(crate) aliases: &'static [&'static str],
+    /// Linux input event code, X keycodes are offset by 8.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) evdev: u16,
+    /// Windows virtual key code, 0 for none.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) vk: u16,
+    /// X keysym of the base level on the US layout, 0 for none.
+    pub(crate) keysym: u32,
+    /// Character typed with Shift on the US layout.
+    pub(crate) shifted: Option<char>,
+}
+
+const fn key(
+    key: Keyboard,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    evdev: u16,
+    vk: u16,
+    keysym: u32,
+    shifted: Option<char>,
+) -> KeyInfo {
+    KeyInfo {
+        key,
+        name,
+        aliases,
+        evdev,
+        vk,
+        keysym,
+        shifted,
+    }
+}
+
+/// Every key but `Other`, in the order of the enum.
+/// Key, name, aliases, evdev code, virtual key code, X keysym, shifted character.
+#[rustfmt::skip]
+pub(crate) const KEYS: &[KeyInfo] = {
+    use Keyboard::*;
+    &[
+        key(A, "A", &[], 30, 0x41, 0x61, Some('A')),
+        key(B, "B", &[], 48, 0x42, 0x62, Some('B')),
+        key(C, "C", &[], 46, 0x43, 0x63, Some('C')),
+        key(D, "D", &[], 32, 0x44, 0x64, Some('D')),
+        key(E, "E", &[], 18, 0x45, 0x65, Some('E')),
+        key(F, "F", &[], 33, 0x46, 0x66, Some('F')),
+        key(G, "G", &[], 34, 0x47, 0x67, Some('G')),
+        key(H, "H", &[], 35, 0x48, 0x68, Some('H')),
+        key(I, "I", &[], 23, 0x49, 0x69, Some('I')),
+        key(J, "J", &[], 36, 0x4a, 0x6a, Some('J')),
+        key(K, "K", &[], 37, 0x4b, 0x6b, Some('K')),
+        key(L, "L", &[], 38, 0x4c, 0x6c, Some('L')),
+        key(M, "M", &[], 50, 0x4d, 0x6d, Some('M')),
+        key(N, "N", &[], 49, 0x4e, 0x6e, Some('N')),
+        key(O, "O", &[], 24, 0x4f, 0x6f, Some('O')),
+        key(P, "P", &[], 25, 0x50, 0x70, Some('P')),
+        key(Q, "Q", &[], 16, 0x51, 0x71, Some('Q')),
+        key(R, "R", &[], 19, 0x52, 0x72, Some('R')),
+        key(S, "S", &[], 31, 0x53, 0x73, Some('S')),
+        key(T, "T", &[], 20, 0x54, 0x74, Some('T')),
+        key(U, "U", &[], 22, 0x55, 0x75, Some('U')),
+        key(V, "V", &[], 47, 0x56, 0x76, Some('V')),
+        key(W, "W", &[], 17, 0x57, 0x77, Some('W')),
+        key(X, "X", &[], 45, 0x58, 0x78, Some('X')),
+        key(Y, "Y", &[], 21, 0x59, 0x79, Some('Y')),
+        key(Z, "Z", &[], 44, 0x5a, 0x7a, Some('Z')),
+        key(Number0, "Number0", &["0"], 11, 0x30, 0x30, Some(')')),
+        key(Number1, "Number1", &["1"], 2, 0x31, 0x31, Some('!')),
+        key(Number2, "Number2", &["2"], 3, 0x32, 0x32, Some('@')),
+        key(Number3, "Number3", &["3"], 4, 0x33, 0x33, Some('#')),
+        key(Number4, "Number4", &["4"], 5, 0x34, 0x34, Some('$')),
+        key(Number5, "Number5", &["5"], 6, 0x35, 0x35, Some('%')),
+        key(Number6, "Number6", &["6"], 7, 0x36, 0x36, Some('^')),
+        key(Number7, "Number7", &["7"], 8, 0x37, 0x37, Some('&')),
+        key(Number8, "Number8", &["8"], 9, 0x38, 0x38, Some('*')),
+        key(Number9, "Number9", &["9"], 10, 0x39, 0x39, Some('(')),
+        key(LeftAlt, "LeftAlt", &[], 56, 0xa4, 0xffe9, None),
+        key(RightAlt, "RightAlt", &[], 100, 0xa5, 0xffea, None),
+        key(LeftShift, "LeftShift", &[], 42, 0xa0, 0xffe1, None),
+        key(RightShift, "RightShift", &[], 54, 0xa1, 0xffe2, None),
+        key(LeftControl, "LeftControl", &[], 29, 0xa2, 0xffe3, None),
+        key(RightControl, "RightControl", &[], 97, 0xa3, 0xffe4, None),
+        key(BackSpace, "BackSpace", &[], 14, 0x08, 0xff08, None),
+        key(Tab, "Tab", &["\t"], 15, 0x09, 0xff09, None),
+        key(Enter, "Enter", &["\n"], 28, 0x0d, 0xff0d, None),
+        key(Escape, "Escape", &[], 1, 0x1b, 0xff1b, None),
+        key(Space, "Space", &[" "], 57, 0x20, 0x20, None),
+        key(PageUp, "PageUp", &[], 104, 0x21, 0xff55, None),
+        key(PageDown, "PageDown", &[], 109, 0x22, 0xff56, None),
+        key(Home, "Home", &[], 102, 0x24, 0xff50, None),
+        key(Left, "Left", &[], 105, 0x25, 0xff51, None),
+        key(Up, "Up", &[], 103, 0x26, 0xff52, None),
+        key(Right, "Right", &[], 106, 0x27, 0xff53, None),
+        key(Down, "Down", &[], 108, 0x28, 0xff54, None),
+        key(Print, "Print", &[], 210, 0x2a, 0xff61, None),
+        key(PrintScreen, "PrintScreen", &[], 99, 0x2c, 0xff61, None),
+        key(Insert, "Insert", &[], 110, 0x2d, 0xff63, None),
+        key(Delete, "Delete", &[], 111, 0x2e, 0xffff, None),
+        key(LeftWindows, "LeftWindows", &[], 125, 0x5b, 0xffeb, None),
+        key(RightWindows, "RightWindows", &[], 126, 0x5c, 0xffec, None),
+        key(Comma, "Comma", &[","], 51, 0xbc, 0x2c, Some('<')),
+        key(Period, "Period", &["."], 52, 0xbe, 0x2e, Some('>')),
+        key(Slash, "Slash", &["/", "?"], 53, 0xbf, 0x2f, Some('?')),
+        key(SemiColon, "SemiColon", &[";", ":"], 39, 0xba, 0x3b, Some(':')),
+        key(Apostrophe, "Apostrophe", &["'", "\""], 40, 0xde, 0x27, Some('"')),
+        key(LeftBrace, "LeftBrace", &["["], 26, 0xdb, 0x5b, Some('{')),
+        key(BackwardSlash, "BackwardSlash", &["\\"], 43, 0xdc, 0x5c, Some('|')),
+        key(RightBrace, "RightBrace", &["]"], 27, 0xdd, 0x5d, Some('}')),
+        key(Grave, "Grave", &["`"], 41, 0xc0, 0x60, Some('~')),
+        key(Minus, "Minus", &["-"], 12, 0xbd, 0x2d, Some('_')),
+        key(Equal, "Equal", &["="], 13, 0xbb, 0x3d, Some('+')),
+        key(F1, "F1", &[], 59, 0x70, 0xffbe, None),
+        key(F2, "F2", &[], 60, 0x71, 0xffbf, None),
+        key(F3, "F3", &[], 61, 0x72, 0xffc0, None),
+        key(F4, "F4", &[], 62, 0x73, 0xffc1, None),
+        key(F5, "F5", &[], 63, 0x74, 0xffc2, None),
+        key(F6, "F6", &[], 64, 0x75, 0xffc3, None),
+        key(F7, "F7", &[], 65, 0x76, 0xffc4, None),
+        key(F8, "F8", &[], 66, 0x77, 0xffc5, None),
+        key(F9, "F9", &[], 67, 0x78, 0xffc6, None),
+        key(F10, "F10", &[], 68, 0x79, 0xffc7, None),
+        key(F11, "F11", &[], 87, 0x7a, 0xffc8, None),
+        key(F12, "F12", &[], 88, 0x7b, 0xffc9, None),
+        key(F13, "F13", &[], 183, 0x7c, 0xffca, None),
+        key(F14, "F14", &[], 184, 0x7d, 0xffcb, None),
+        key(F15, "F15", &[], 185, 0x7e, 0xffcc, None),
+        key(F16, "F16", &[], 186, 0x7f, 0xffcd, None),
+        key(F17, "F17", &[], 187, 0x80, 0xffce, None),
+        key(F18, "F18", &[], 188, 0x81, 0xffcf, None),
+        key(F19, "F19", &[], 189, 0x82, 0xffd0, None),
+        key(F20, "F20", &[], 190, 0x83, 0xffd1, None),
+        key(F21, "F21", &[], 191, 0x84, 0xffd2, None),
+        key(F22, "F22", &[], 192, 0x85, 0xffd3, None),
+        key(F23, "F23", &[], 193, 0x86, 0xffd4, None),
+        key(F24, "F24", &[], 194, 0x87, 0xffd5, None),
+        key(NumLock, "NumLock", &[], 69, 0x90, 0xff7f, None),
+        key(ScrollLock, "ScrollLock", &[], 70, 0x91, 0xff14, None),
+        key(CapsLock, "CapsLock", &[], 58, 0x14, 0xffe5, None),
+        key(Numpad0, "Numpad0", &[], 82, 0x60, 0xffb0, None),
+        key(Numpad1, "Numpad1", &[], 79, 0x61, 0xffb1, None),
+        key(Numpad2, "Numpad2", &[], 80, 0x62, 0xffb2, None),
+        key(Numpad3, "Numpad3", &[], 81, 0x63, 0xffb3, None),
+        key(Numpad4, "Numpad4", &[], 75, 0x64, 0xffb4, None),
+        key(Numpad5, "Numpad5", &[], 76, 0x65, 0xffb5, None),
+        key(Numpad6, "Numpad6", &[], 77, 0x66, 0xffb6, None),
+        key(Numpad7, "Numpad7", &[], 71, 0x67, 0xffb7, None),
+        key(Numpad8, "Numpad8", &[], 72, 0x68, 0xffb8, None),
+        key(Numpad9, "Numpad9", &[], 73, 0x69, 0xffb9, None),
+        key(Multiply, "Multiply", &[], 55, 0x6a, 0xffaa, None),
+        key(Add, "Add", &[], 78, 0x6b, 0xffab, None),
+        key(Separator, "Separator", &[], 121, 0x6c, 0xffac, None),
+        key(Subtract, "Subtract", &[], 74, 0x6d, 0xffad, None),
+        key(Decimal, "Decimal", &[], 83, 0x6e, 0xffae, None),
+        key(Divide, "Divide", &[], 98, 0x6f, 0xffaf, None),
+        // Shares the virtual key with Enter, told apart by the extended flag.
+        key(NumpadEnter, "NumpadEnter", &[], 96, 0x0d, 0xff8d, None),
+        key(MediaPlayPause, "MediaPlayPause", &[], 164, 0xb3, 0x1008ff14, None),
+        key(MediaNext, "MediaNext", &[], 163, 0xb0, 0x1008ff17, None),
+        key(MediaPrevious, "MediaPrevious", &[], 165, 0xb1, 0x1008ff16, None),
+        key(MediaStop, "MediaStop", &[], 166, 0xb2, 0x1008ff15, None),
+        key(VolumeMute, "VolumeMute", &[], 113, 0xad, 0x1008ff12, None),
+        key(VolumeDown, "VolumeDown", &[], 114, 0xae, 0x1008ff11, None),
+        key(VolumeUp, "VolumeUp", &[], 115, 0xaf, 0x1008ff13, None),
+        // No virtual key, the firmware handles these.
+        key(BrightnessDown, "BrightnessDown", &[], 224, 0x00, 0x1008ff03, None),
+        key(BrightnessUp, "BrightnessUp", &[], 225, 0x00, 0x1008ff02, None),
+        key(BrowserBack, "BrowserBack", &[], 158, 0xa6, 0x1008ff26, None),
+        key(BrowserForward, "BrowserForward", &[], 159, 0xa7, 0x1008ff27, None),
+        key(BrowserHome, "BrowserHome", &[], 172, 0xac, 0x1008ff18, None),
+        key(BrowserRefresh, "BrowserRefresh", &[], 173, 0xa8, 0x1008ff73, None),
+        key(Calculator, "Calculator", &[], 140, 0xb7, 0x1008ff1d, None),
+        key(Mail, "Mail", &[], 155, 0xb4, 0x1008ff19, None),
+        // Missing on the US layout.
+        key(ThatThingy, "ThatThingy", &["<", ">"], 86, 0xe2, 0, None),
+    ]
+};
+
+impl Keyboard {
+    /// Every key but the ones carrying a code.
+    pub fn all() -> impl Iterator<Item = Keyboard> {
+        KEYS.iter().map(|info| info.key)
+    }
+
+    /// Name of the key as accepted by `from_str`, just the variant for the ones carrying a code.
+    pub fn name(self) -> &'static str {
+        match self {
+            Keyboard::Other(_) => "Other",
+            Keyboard::Scancode(_) => "Scancode",
+            Keyboard::Native(_) => "Native",
+            key => key.info().map_or("", |info| info.name),
+        }
+    }
+
+    pub(crate) fn info(self) -> Option<&'static KeyInfo> {
+        KEYS.iter().find(|info| info.key == self)
+    }
 }
 
 impl FromStr for Keyboard {
     type Err = ();
 
+    /// Name or alias of the key, or a code such as `Native(183)` or `Scancode(0xe05f)`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parsed = match s {
-            "A" => Keyboard::A,
-            "B" => Keyboard::B,
-            "C" => Keyboard::C,
-            "D" => Keyboard::D,
-            "E" => Keyboard::E,
-            "F" => Keyboard::F,
-            "G" => Keyboard::G,
-            "H" => Keyboard::H,
-            "I" => Keyboard::I,
-            "J" => Keyboard::J,
-            "K" => Keyboard::K,
-            "L" => Keyboard::L,
-            "M" => Keyboard::M,
-            "N" => Keyboard::N,
-            "O" => Keyboard::O,
-            "P" => Keyboard::P,
-            "Q" => Keyboard::Q,
-            "R" => Keyboard::R,
-            "S" => Keyboard::S,
-            "T" => Keyboard::T,
-            "U" => Keyboard::U,
-            "V" => Keyboard::V,
-            "W" => Keyboard::W,
-            "X" => Keyboard::X,
-            "Y" => Keyboard::Y,
-            "Z" => Keyboard::Z,
-            "0" => Keyboard::Number0,
-            "1" => Keyboard::Number1,
-            "2" => Keyboard::Number2,
-            "3" => Keyboard::Number3,
-            "4" => Keyboard::Number4,
-            "5" => Keyboard::Number5,
-            "6" => Keyboard::Number6,
-            "7" => Keyboard::Number7,
-            "8" => Keyboard::Number8,
-            "9" => Keyboard::Number9,
-            "LeftAlt" => Keyboard::LeftAlt,
-            "RightAlt" => Keyboard::RightAlt,
-            "LeftShift" => Keyboard::LeftShift,
-            "RightShift" => Keyboard::RightShift,
-            "LeftControl" => Keyboard::LeftControl,
-            "RightControl" => Keyboard::RightControl,
-            "BackSpace" => Keyboard::BackSpace,
-            "Tab" | "	" => Keyboard::Tab,
-            "Enter" | "\n" => Keyboard::Enter,
-            "Escape" => Keyboard::Escape,
-            "Space" | " " => Keyboard::Space,
-            "PageUp" => Keyboard::PageUp,
-            "PageDown" => Keyboard::PageDown,
-            "Home" => Keyboard::Home,
-            "Left" => Keyboard::Left,
-            "Up" => Keyboard::Up,
-            "Right" => Keyboard::Right,
-            "Down" => Keyboard::Down,
-            "Print" => Keyboard::Print,
-            "PrintScreen" => Keyboard::PrintScreen,
-            "Insert" => Keyboard::Insert,
-            "Delete" => Keyboard::Delete,
-            "LeftWindows" => Keyboard::LeftWindows,
-            "RightWindows" => Keyboard::RightWindows,
-            "Comma" | "," => Keyboard::Comma,
-            "Period" | "." => Keyboard::Period,
-            "Slash" | "/" | "?" => Keyboard::Slash,
-            "SemiColon" | ";" | ":" => Keyboard::SemiColon,
-            "Apostrophe" | "'" | "\"" => Keyboard::Apostrophe,
-            "LeftBrace" | "[" => Keyboard::LeftBrace,
-            "BackwardSlash" | "\\" => Keyboard::BackwardSlash,
-            "RightBrace" | "]" => Keyboard::RightBrace,
-            "Grave" | "`" => Keyboard::Grave,
-            "F1" => Keyboard::F1,
-            "F2" => Keyboard::F2,
-            "F3" => Keyboard::F3,
-            "F4" => Keyboard::F4,
-            "F5" => Keyboard::F5,
-            "F6" => Keyboard::F6,
-            "F7" => Keyboard::F7,
-            "F8" => Keyboard::F8,
-            "F9" => Keyboard::F9,
-            "F10" => Keyboard::F10,
-            "F11" => Keyboard::F11,
-            "F12" => Keyboard::F12,
-            "F13" => Keyboard::F13,
-            "F14" => Keyboard::F14,
-            "F15" => Keyboard::F15,
-            "F16" => Keyboard::F16,
-            "F17" => Keyboard::F17,
-            "F18" => Keyboard::F18,
-            "F19" => Keyboard::F19,
-            "F20" => Keyboard::F20,
-            "F21" => Keyboard::F21,
-            "F22" => Keyboard::F22,
-            "F23" => Keyboard::F23,
-            "F24" => Keyboard::F24,
-            "NumLock" => Keyboard::NumLock,
-            "ScrollLock" => Keyboard::ScrollLock,
-            "CapsLock" => Keyboard::CapsLock,
-            "Numpad0" => Keyboard::Numpad0,
-            "Numpad1" => Keyboard::Numpad1,
-            "Numpad2" => Keyboard::Numpad2,
-            "Numpad3" => Keyboard::Numpad3,
-            "Numpad4" => Keyboard::Numpad4,
-            "Numpad5" => Keyboard::Numpad5,
-            "Numpad6" => Keyboard::Numpad6,
-            "Numpad7" => Keyboard::Numpad7,
-            "Numpad8" => Keyboard::Numpad8,
-            "Numpad9" => Keyboard::Numpad9,
-            "Multiply" => Keyboard::Multiply,
-            "Add" => Keyboard::Add,
-            "Separator" => Keyboard::Separator,
-            "Subtract" => Keyboard::Subtract,
-            "Decimal" => Keyboard::Decimal,
-            "Divide" => Keyboard::Divide,
-            "<" | ">" => Keyboard::ThatThingy,
+        if let Some(info) = KEYS
+            .iter()
+            .find(|info| info.name == s || info.aliases.contains(&s))
+        {
+            return Ok(info.key);
+        }
+        let (variant, code) = s.strip_suffix(')').ok_or(())?.split_once('(').ok_or(())?;
+        let code = code.trim();
+        let code = match code.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => code.parse(),
+        }
+        .map_err(|_| ())?;
+        match variant {
+            "Other" => i32::try_from(code).map(Keyboard::Other),
+            "Scancode" => u32::try_from(code).map(Keyboard::Scancode),
+            "Native" => u32::try_from(code).map(Keyboard::Native),
             _ => return Err(()),
-        };
-        Ok(parsed)
+        }
+        .map_err(|_| ())
+    }
+}
+
+// In YAML the keys carrying a code are written as in `from_str`, e.g. `Native(183)`,
+// the derived `Native: 183` is accepted too.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeyboardRepr {
+    Name(String),
+    Code(Code),
+}
+
+#[derive(Serialize, Deserialize)]
+enum Code {
+    Other(i32),
+    Scancode(u32),
+    Native(u32),
+}
+
+impl TryFrom<KeyboardRepr> for Keyboard {
+    type Error = String;
+
+    fn try_from(repr: KeyboardRepr) -> Result<Self, Self::Error> {
+        match repr {
+            KeyboardRepr::Name(name) => {
+                Keyboard::from_str(&name).map_err(|_| format!("Unknown key: {}", name))
+            }
+            KeyboardRepr::Code(Code::Other(code)) => Ok(Keyboard::Other(code)),
+            KeyboardRepr::Code(Code::Scancode(code)) => Ok(Keyboard::Scancode(code)),
+            KeyboardRepr::Code(Code::Native(code)) => Ok(Keyboard::Native(code)),
+        }
+    }
+}
+
+impl From<Keyboard> for KeyboardRepr {
+    fn from(key: Keyboard) -> Self {
+        // Debug matches `from_str` for every key.
+        KeyboardRepr::Name(format!("{:?}", key))
     }
 }
 
 impl fmt::Display for Keyboard {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Keyboard::Other(_) | Keyboard::Scancode(_) | Keyboard::Native(_) = self {
+            // The code is already there.
+            return write!(f, "{:?}", self);
+        }
         let code: i32 = (*self).into();
         f.write_fmt(format_args!("{:?}({})", self, code))?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::keyboard::KEYS;
+    use crate::Keyboard;
+    use std::str::FromStr;
+
+    #[test]
+    fn key_table() {
+        let keys: Vec<Keyboard> = Keyboard::all().collect();
+        assert!(
+            keys.windows(2).all(|pair| pair[0] < pair[1]),
+            "keys out of order"
+        );
+        for (index, info) in KEYS.iter().enumerate() {
+            assert_eq!(format!("{:?}", info.key), info.name);
+            assert_eq!(info.name, info.key.name());
+            assert_eq!(Ok(info.key), Keyboard::from_str(info.name));
+            for alias in info.aliases {
+                assert_eq!(Ok(info.key), Keyboard::from_str(alias), "{:?}", alias);
+            }
+            for other in &KEYS[..index] {
+                assert_ne!(info.evdev, other.evdev, "{} {}", info.name, other.name);
+                if info.vk != 0 && info.key != Keyboard::NumpadEnter {
+                    assert_ne!(info.vk, other.vk, "{} {}", info.name, other.name);
+                }
+            }
+        }
+        assert_eq!("Other", Keyboard::Other(0x2ff).name());
+        assert_eq!(Err(()), Keyboard::from_str("Other"));
+    }
+
+    #[test]
+    fn codes() {
+        use crate::Keyboard::*;
+        assert_eq!(Ok(Native(183)), Keyboard::from_str("Native(183)"));
+        assert_eq!(Ok(Scancode(0xe05f)), Keyboard::from_str("Scancode(0xe05f)"));
+        assert_eq!(Ok(Other(-1)), Keyboard::from_str("Other(-1)"));
+        assert_eq!(Err(()), Keyboard::from_str("Native(-1)"));
+        assert_eq!(Err(()), Keyboard::from_str("Native(183"));
+        assert_eq!(Err(()), Keyboard::from_str("A(1)"));
+        assert_eq!("Native", Native(183).name());
+        assert_eq!("Native(183)", Native(183).to_string());
+
+        let keys: Vec<Keyboard> =
+            serde_yaml::from_str("[A, ',', Native(183), {Scancode: 28}]").unwrap();
+        assert_eq!(vec![A, Comma, Native(183), Scancode(28)], keys);
+        let yaml = serde_yaml::to_string(&keys).unwrap();
+        assert_eq!(keys, serde_yaml::from_str::<Vec<Keyboard>>(&yaml).unwrap());
+        assert!(serde_yaml::from_str::<Keyboard>("Nope").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::details::{Binds, HotkeyCallback};
+use crate::{Action, Event, Hotkey, InhibitEvent, Keyboard, Mouse, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Name of the layer at the bottom of the stack, the plain binds belong to it.
+pub const BASE_LAYER: &str = "base";
+
+/// How a key activates a layer, see `layer_key`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayerMode {
+    /// Active while the key is held.
+    Momentary,
+    /// Every press activates or deactivates the layer.
+    Toggle,
+    /// The layer replaces all the active ones but the base one.
+    Switch,
+}
+
+/// Binds of a layer other than the base one.
+#[derive(Default)]
+pub(crate) struct LayerBinds {
+    pub(crate) keys: HashMap<Keyboard, Binds<Arc<Action>>>,
+    pub(crate) buttons: HashMap<Mouse, Binds<Arc<Action>>>,
+    pub(crate) hotkeys: Binds<(Hotkey, HotkeyCallback)>,
+}
+
+/// Active layers above the base one, the last one is on top.
+#[derive(Default)]
+pub(crate) struct LayerStack {
+    layers: Vec<String>,
+    /// Layer keys being held, hotkeys are matched as if they were not pressed.
+    pub(crate) held: Vec<Keyboard>,
+}
+
+impl LayerStack {
+    pub(crate) fn push(&mut self, layer: &str) {
+        if layer != BASE_LAYER {
+            self.layers.push(layer.into());
+        }
+    }
+
+    /// Removes the topmost occurrence of the layer.
+    pub(crate) fn pop(&mut self, layer: &str) {
+        if let Some(index) = self.layers.iter().rposition(|active| active == layer) {
+            self.layers.remove(index);
+        }
+    }
+
+    pub(crate) fn toggle(&mut self, layer: &str) {
+        if self.layers.iter().any(|active| active == layer) {
+            self.layers.retain(|active| active != layer);
+        } else {
+            self.push(layer);
+        }
+    }
+
+    pub(crate) fn switch(&mut self, layer: &str) {
+        self.layers.clear();
+        self.push(layer);
+    }
+
+    pub(crate) fn top(&self) -> &str {
+        self.layers.last().map_or(BASE_LAYER, String::as_str)
+    }
+
+    /// Active layers top first, the base one included.
+    pub(crate) fn active(&self) -> Vec<String> {
+        self.layers
+            .iter()
+            .rev()
+            .cloned()
+            .chain(std::iter::once(BASE_LAYER.to_string()))
+            .collect()
+    }
+}
+
+/// Action activating the layer according to the mode, repeats of the key are ignored.
+/// The key only switches layers, its presses are inhibited.
+pub(crate) fn layer_action(stack: Arc<Mutex<LayerStack>>, layer: &str, mode: LayerMode) -> Action {
+    let layer = layer.to_string();
+    let held = AtomicBool::new(false);
+    Action {
+        callback: Box::new(move |event, state| {
+            let mut stack = stack.lock().unwrap();
+            match state {
+                State::Pressed => {
+                    if held.swap(true, Ordering::Relaxed) {
+                        return;
+                    }
+                    if let Event::Keyboard(key) = event {
+                        stack.held.push(key);
+                    }
+                    match mode {
+                        LayerMode::Momentary => stack.push(&layer),
+                        LayerMode::Toggle => stack.toggle(&layer),
+                        LayerMode::Switch => stack.switch(&layer),
+                    }
+                }
+                State::Released => {
+                    held.store(false, Ordering::Relaxed);
+                    if let Event::Keyboard(key) = event {
+                        stack.held.retain(|held| *held != key);
+                    }
+                    if mode == LayerMode::Momentary {
+                        stack.pop(&layer);
+                    }
+                }
+            }
+        }),
+        inhibit: InhibitEvent::Yes,
+        defer: false,
+        sequencer: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layer::{LayerStack, BASE_LAYER};
+
+    #[test]
+    fn stack() {
+        let mut stack = LayerStack::default();
+        assert_eq!(BASE_LAYER, stack.top());
+        stack.push("nav");
+        stack.toggle("num");
+        assert_eq!(vec!["num", "nav", BASE_LAYER], stack.active());
+        stack.toggle("num");
+        stack.pop("nav");
+        assert_eq!(BASE_LAYER, stack.top());
+        stack.push("nav");
+        stack.switch("media");
+        assert_eq!(vec!["media", BASE_LAYER], stack.active());
+        stack.switch(BASE_LAYER);
+        assert_eq!(vec![BASE_LAYER], stack.active());
+    }
+}
@@ -0,0 +1,350 @@
+use crate::context::context;
+use crate::keyboard::KEYS;
+use crate::Keyboard;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Modifiers that have to be held for a key to produce a character.
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+pub enum Level {
+    Base,
+    Shift,
+    AltGr,
+    ShiftAltGr,
+}
+
+impl Level {
+    /// Keys to hold, AltGr is the right Alt.
+    pub fn modifiers(self) -> &'static [Keyboard] {
+        match self {
+            Level::Base => &[],
+            Level::Shift => &[Keyboard::LeftShift],
+            Level::AltGr => &[Keyboard::RightAlt],
+            Level::ShiftAltGr => &[Keyboard::LeftShift, Keyboard::RightAlt],
+        }
+    }
+}
+
+/// Physical keys producing characters, by rows as on the US layout.
+const ROWS: [&[Keyboard]; 4] = {
+    use Keyboard::*;
+    [
+        &[
+            Grave, Number1, Number2, Number3, Number4, Number5, Number6, Number7, Number8, Number9,
+            Number0, Minus, Equal,
+        ],
+        &[Q, W, E, R, T, Y, U, I, O, P, LeftBrace, RightBrace],
+        &[
+            A,
+            S,
+            D,
+            F,
+            G,
+            H,
+            J,
+            K,
+            L,
+            SemiColon,
+            Apostrophe,
+            BackwardSlash,
+        ],
+        &[ThatThingy, Z, X, C, V, B, N, M, Comma, Period, Slash],
+    ]
+};
+
+/// Keyboard layout, maps characters to the keys producing them and back.
+/// Dead keys are not supported, characters requiring those are typed as unicode.
+/// ```
+/// use mki::*;
+///
+/// fn german() {
+///   let layout = Layout::by_name("de").unwrap();
+///   assert_eq!(Some((Keyboard::Y, Level::Base)), layout.key('z'));
+///   assert_eq!(Some('@'), layout.char(Keyboard::Q, Level::AltGr));
+///   set_layout(layout);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Layout {
+    name: String,
+    chars: HashMap<(Keyboard, Level), char>,
+    keys: HashMap<char, (Keyboard, Level)>,
+}
+
+impl Layout {
+    /// Layout from the characters of each level, `levels` contain a string per row of `ROWS`.
+    /// Space stands for no character.
+    fn from_rows(name: &str, levels: &[(Level, [&str; 4])]) -> Self {
+        let mut chars = Vec::new();
+        for (level, rows) in levels {
+            for (keys, row) in ROWS.iter().zip(rows.iter()) {
+                debug_assert_eq!(keys.len(), row.chars().count(), "{} {:?}", name, row);
+                for (key, c) in keys.iter().zip(row.chars()) {
+                    if c != ' ' {
+                        chars.push(((*key, *level), c));
+                    }
+                }
+            }
+        }
+        Self::new(name, chars)
+    }
+
+    /// Layout from the characters produced by the keys, whitespace is added.
+    pub fn new(name: &str, chars: impl IntoIterator<Item = ((Keyboard, Level), char)>) -> Self {
+        let mut layout = Layout {
+            name: name.into(),
+            chars: HashMap::new(),
+            keys: HashMap::new(),
+        };
+        let whitespace = [
+            ((Keyboard::Space, Level::Base), ' '),
+            ((Keyboard::Enter, Level::Base), '\n'),
+            ((Keyboard::Tab, Level::Base), '\t'),
+        ];
+        for (key_level, c) in whitespace.iter().copied().chain(chars) {
+            layout.chars.insert(key_level, c);
+            // Prefer the lower levels when multiple keys produce the same character.
+            let existing = layout.keys.entry(c).or_insert(key_level);
+            if key_level.1 < existing.1 {
+                *existing = key_level;
+            }
+        }
+        layout
+    }
+
+    /// From the key table, Latin-1 keysyms are the characters of the base level.
+    pub fn us() -> Self {
+        let chars = KEYS.iter().flat_map(|info| {
+            let base = match info.keysym {
+                0x20..=0x7e => std::char::from_u32(info.keysym),
+                _ => None,
+            };
+            let base = base.map(|c| ((info.key, Level::Base), c));
+            let shifted = info.shifted.map(|c| ((info.key, Level::Shift), c));
+            base.into_iter().chain(shifted)
+        });
+        Self::new("us", chars)
+    }
+
+    pub fn uk() -> Self {
+        Self::from_rows(
+            "uk",
+            &[
+                (
+                    Level::Base,
+                    [
+                        "`1234567890-=",
+                        "qwertyuiop[]",
+                        "asdfghjkl;'#",
+                        "\\zxcvbnm,./",
+                    ],
+                ),
+                (
+                    Level::Shift,
+                    [
+                        "¬!\"£$%^&*()_+",
+                        "QWERTYUIOP{}",
+                        "ASDFGHJKL:@~",
+                        "|ZXCVBNM<>?",
+                    ],
+                ),
+                (
+                    Level::AltGr,
+                    [
+                        "¦   €        ",
+                        "  é   úíó   ",
+                        "á           ",
+                        "           ",
+                    ],
+                ),
+            ],
+        )
+    }
+
+    pub fn de() -> Self {
+        Self::from_rows(
+            "de",
+            &[
+                (
+                    Level::Base,
+                    [
+                        " 1234567890ß ",
+                        "qwertzuiopü+",
+                        "asdfghjklöä#",
+                        "<yxcvbnm,.-",
+                    ],
+                ),
+                (
+                    Level::Shift,
+                    [
+                        "°!\"§$%&/()=? ",
+                        "QWERTZUIOPÜ*",
+                        "ASDFGHJKLÖÄ'",
+                        ">YXCVBNM;:_",
+                    ],
+                ),
+                (
+                    Level::AltGr,
+                    [
+                        "  ²³   {[]}\\ ",
+                        "@ €        ~",
+                        "            ",
+                        "|      µ   ",
+                    ],
+                ),
+            ],
+        )
+    }
+
+    pub fn fr() -> Self {
+        Self::from_rows(
+            "fr",
+            &[
+                (
+                    Level::Base,
+                    [
+                        "²&é\"'(-è_çà)=",
+                        "azertyuiop $",
+                        "qsdfghjklmù*",
+                        "<wxcvbn,;:!",
+                    ],
+                ),
+                (
+                    Level::Shift,
+                    [
+                        " 1234567890°+",
+                        "AZERTYUIOP £",
+                        "QSDFGHJKLM%µ",
+                        ">WXCVBN?./§",
+                    ],
+                ),
+                (
+                    Level::AltGr,
+                    [
+                        "   #{[| \\^@]}",
+                        "  €         ",
+                        "            ",
+                        "           ",
+                    ],
+                ),
+            ],
+        )
+    }
+
+    pub fn dvorak() -> Self {
+        Self::from_rows(
+            "dvorak",
+            &[
+                (
+                    Level::Base,
+                    [
+                        "`1234567890[]",
+                        "',.pyfgcrl/=",
+                        "aoeuidhtns-\\",
+                        " ;qjkxbmwvz",
+                    ],
+                ),
+                (
+                    Level::Shift,
+                    [
+                        "~!@#$%^&*(){}",
+                        "\"<>PYFGCRL?+",
+                        "AOEUIDHTNS_|",
+                        " :QJKXBMWVZ",
+                    ],
+                ),
+            ],
+        )
+    }
+
+    /// One of the bundled layouts: us, uk, de, fr or dvorak.
+    pub fn by_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "us" => Self::us(),
+            "uk" | "gb" => Self::uk(),
+            "de" => Self::de(),
+            "fr" => Self::fr(),
+            "dvorak" => Self::dvorak(),
+            _ => return None,
+        })
+    }
+
+    /// Layout currently configured in the X server.
+    #[cfg(target_os = "linux")]
+    pub fn from_xkb() -> Self {
+        let keys = ROWS.iter().flat_map(|row| row.iter().copied());
+        Self::new(
+            "xkb",
+            crate::linux::keyboard_mouse::xkb_chars(
+                keys,
+                &[Level::Base, Level::Shift, Level::AltGr, Level::ShiftAltGr],
+            ),
+        )
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Key and the modifiers producing given character.
+    pub fn key(&self, c: char) -> Option<(Keyboard, Level)> {
+        self.keys.get(&c).copied()
+    }
+
+    /// Character produced by the key with given modifiers held.
+    pub fn char(&self, key: Keyboard, level: Level) -> Option<char> {
+        self.chars.get(&(key, level)).copied()
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::us()
+    }
+}
+
+/// Replaces the layout of the default context used by `Sequence::text`, US by default.
+pub fn set_layout(layout: Layout) {
+    context().set_layout(layout)
+}
+
+/// Layout of the default context.
+pub fn layout() -> Arc<Layout> {
+    context().layout()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Keyboard, Layout, Level};
+
+    #[test]
+    fn bundled_layouts() {
+        for name in ["us", "uk", "de", "fr", "dvorak"].iter() {
+            let layout = Layout::by_name(name).unwrap();
+            assert_eq!(*name, layout.name());
+            for (c, key) in [
+                ('a', Keyboard::A),
+                ('1', Keyboard::Number1),
+                (' ', Keyboard::Space),
+            ]
+            .iter()
+            {
+                let (found, level) = layout.key(*c).unwrap();
+                assert_eq!(Some(*c), layout.char(found, level));
+                if *name == "us" || *name == "uk" || *name == "de" {
+                    assert_eq!(*key, found, "{} {}", name, c);
+                }
+            }
+        }
+
+        let us = Layout::us();
+        assert_eq!(Some((Keyboard::Number2, Level::Shift)), us.key('@'));
+        assert_eq!(Some((Keyboard::Q, Level::AltGr)), Layout::de().key('@'));
+        assert_eq!(Some((Keyboard::Q, Level::Base)), Layout::fr().key('a'));
+        assert_eq!(
+            Some((Keyboard::SemiColon, Level::Base)),
+            Layout::dvorak().key('s')
+        );
+        assert_eq!(None, us.key('é'));
+    }
+}
@@ -1,32 +1,42 @@
+mod backend;
+mod bind_handle;
+mod context;
 pub(crate) mod details;
+mod error;
+mod gesture;
+mod hotkey;
 
 mod keyboard;
+mod layer;
+mod layout;
 #[cfg(target_os = "linux")]
 mod linux;
 mod mouse;
 mod parse;
+mod recorder;
 mod sequence;
 #[cfg(target_os = "windows")]
 mod windows;
 
-#[cfg(target_os = "linux")]
-use crate::linux::keyboard_mouse::kimpl;
-#[cfg(target_os = "linux")]
-use crate::linux::keyboard_mouse::mimpl;
-#[cfg(target_os = "windows")]
-use crate::windows::keyboard::kimpl;
-#[cfg(target_os = "windows")]
-use crate::windows::mouse::mimpl;
+pub use backend::{set_backend, Backend, Injected, Listener, MockBackend};
+pub use bind_handle::BindHandle;
+pub use context::{context, Context, ContextBuilder};
+pub use error::Error;
+pub use gesture::{Gesture, GestureOptions, GestureThresholds};
+pub use hotkey::{Chords, Hotkey, Leader, Matching, Modifier};
 pub use keyboard::*;
+pub use layer::{LayerMode, BASE_LAYER};
+pub use layout::{layout, set_layout, Layout, Level};
 #[cfg(target_os = "linux")]
 pub use linux::*;
 pub use mouse::*;
 pub use parse::load_config;
-pub use sequence::Sequence;
+pub use recorder::{Recorded, RecordedEvent, Recorder, Recording};
+pub use sequence::{SendHandle, Sequence, SequenceBuilder};
 #[cfg(target_os = "windows")]
 pub use windows::*;
 
-use crate::details::registry;
+use crate::details::started_context;
 use std::fmt;
 use std::sync::Arc;
 
@@ -41,103 +51,170 @@ pub enum State {
 impl Keyboard {
     /// Send an event to Press this key
     pub fn press(&self) {
-        kimpl::press(*self)
+        context().backend().press(*self)
     }
 
     /// Send an event to Release this key
     pub fn release(&self) {
-        kimpl::release(*self)
+        context().backend().release(*self)
     }
     /// Send an event to Click (Press + Release) this key
     pub fn click(&self) {
-        kimpl::click(*self);
+        context().backend().click(*self);
+    }
+
+    /// Version of `press` reporting why the key could not be pressed.
+    pub fn try_press(&self) -> Result<(), Error> {
+        context().backend().try_press(*self)
+    }
+
+    /// Version of `release` reporting why the key could not be released.
+    pub fn try_release(&self) -> Result<(), Error> {
+        context().backend().try_release(*self)
+    }
+
+    /// Version of `click` reporting why the key could not be clicked.
+    pub fn try_click(&self) -> Result<(), Error> {
+        context().backend().try_click(*self)
     }
 
     // Some buttons are toggleable like caps lock.
     /// Whether this KeyboardKey is toggled, applies for some buttons such as Caps Lock
     pub fn is_toggled(&self) -> bool {
-        kimpl::is_toggled(*self)
+        context().backend().is_toggled(*self)
     }
 
     /// Bind an action on this KeyboardKey, action will be invoked on a new thread.
-    pub fn bind(&self, handler: impl Fn(Keyboard) + Send + Sync + 'static) {
+    pub fn bind(&self, handler: impl Fn(Keyboard) + Send + Sync + 'static) -> BindHandle {
         bind_key(*self, Action::handle_kb(handler))
     }
 
-    /// opposite to `bind`. Clears bind
+    /// opposite to `bind`. Clears all the binds of this key.
     pub fn clear_bind(&self) {
         remove_key_bind(*self);
     }
 
     /// Binds an action on this KeyboardKey, a version of `bind` that can do more.
-    pub fn act_on(&self, action: Action) {
+    pub fn act_on(&self, action: Action) -> BindHandle {
         bind_key(*self, action)
     }
 
     /// Whether given KeyboardKey is pressed.
     pub fn is_pressed(&self) -> bool {
-        registry().is_pressed(Event::Keyboard(*self))
+        started_context().is_pressed(Event::Keyboard(*self))
     }
 }
 
 impl Mouse {
     /// Send an event to Press this Button
     pub fn press(&self) {
-        mimpl::press(*self)
+        context().backend().press_button(*self)
     }
 
-    /// Send an event to Release this Button
+    /// Send an event to Click (Press + Release) this Button
     pub fn click(&self) {
-        mimpl::click(*self)
+        context().backend().click_button(*self)
     }
 
-    /// Send an event to Click (Press + Release) this key
+    /// Send an event to Release this Button
     pub fn release(&self) {
-        mimpl::release(*self)
+        context().backend().release_button(*self)
+    }
+
+    /// Version of `press` reporting why the button could not be pressed, e.g. `Error::Unsupported`.
+    pub fn try_press(&self) -> Result<(), Error> {
+        context().backend().try_press_button(*self)
+    }
+
+    /// Version of `release` reporting why the button could not be released, e.g. `Error::Unsupported`.
+    pub fn try_release(&self) -> Result<(), Error> {
+        context().backend().try_release_button(*self)
+    }
+
+    /// Version of `click` reporting why the button could not be clicked, e.g. `Error::Unsupported`.
+    pub fn try_click(&self) -> Result<(), Error> {
+        context().backend().try_click_button(*self)
     }
 
     // On windows this uses absolute coordinates 0;65535 see windows move_to impl note
     pub fn move_to(x: i32, y: i32) {
-        mimpl::move_to(x, y)
+        context().backend().move_to(x, y)
+    }
+
+    /// Version of `move_to` reporting why the pointer could not be moved.
+    pub fn try_move_to(x: i32, y: i32) -> Result<(), Error> {
+        context().backend().try_move_to(x, y)
     }
 
     pub fn move_by(x: i32, y: i32) {
-        mimpl::move_by(x, y)
+        context().backend().move_by(x, y)
+    }
+
+    /// Version of `move_by` reporting why the pointer could not be moved.
+    pub fn try_move_by(x: i32, y: i32) -> Result<(), Error> {
+        context().backend().try_move_by(x, y)
     }
 
     pub fn click_at(&self, x: i32, y: i32) {
-        mimpl::click_at(x, y, *self);
+        context().backend().click_at(x, y, *self);
+    }
+
+    /// Rotate the wheel by given clicks, positive `dy` scrolls up, positive `dx` scrolls right.
+    pub fn scroll(dx: i32, dy: i32) {
+        context().backend().scroll(dx, dy)
+    }
+
+    /// Current pointer position in pixels, on windows note that it differs from `move_to` coordinates.
+    pub fn position() -> (i32, i32) {
+        context().backend().position()
+    }
+
+    /// Version of `position` reporting why the position could not be queried.
+    pub fn try_position() -> Result<(i32, i32), Error> {
+        context().backend().try_position()
     }
 
-    // TODO: this does not work on linux, duh. add it.
+    /// Invoke the callback with the pointer position whenever it moves.
     pub fn track(f: impl Fn(i32, i32) + Send + Sync + 'static) {
-        registry().set_mouse_tracker(Some(Arc::new(Box::new(f))));
+        started_context().track_mouse(f);
+    }
+
+    /// Invoke the callback with the clicks whenever wheel rotates, positive `dy` is up, positive `dx` is right.
+    /// To bind a single direction bind `Mouse::ScrollUp` and the like, which are clicked once per wheel click.
+    pub fn track_scroll(f: impl Fn(i32, i32) + Send + Sync + 'static) {
+        started_context().track_scroll(f);
+    }
+
+    /// Bind an action on the wheel, handler will be invoked on a new thread with the clicks of every rotation.
+    /// Unlike `track_scroll` multiple handlers can be bound, see `bind_scroll` to inhibit the rotation.
+    pub fn bind_scroll(handler: impl Fn(i32, i32) + Send + Sync + 'static) -> BindHandle {
+        bind_scroll(InhibitEvent::No, handler)
     }
 
     /// Bind an action on this MouseButton, action will be invoked on a new thread.
-    pub fn bind(&self, handler: impl Fn(Mouse) + Send + Sync + 'static) {
+    pub fn bind(&self, handler: impl Fn(Mouse) + Send + Sync + 'static) -> BindHandle {
         bind_button(*self, Action::handle_mouse(handler))
     }
 
-    /// opposite to `bind`. Clears bind
+    /// opposite to `bind`. Clears all the binds of this button.
     pub fn clear_bind(&self) {
         remove_button_bind(*self);
     }
 
     /// Binds an action on this MouseButton, a version of `bind` that can do more.
-    pub fn act_on(&self, action: Action) {
+    pub fn act_on(&self, action: Action) -> BindHandle {
         bind_button(*self, action)
     }
 
     /// Whether given MouseButton is pressed.
     pub fn is_pressed(&self) -> bool {
-        registry().is_pressed(Event::Mouse(*self))
+        started_context().is_pressed(Event::Mouse(*self))
     }
 }
 
 #[derive(Clone)]
-/// Works only on windows.
 /// Whether to propagate the event for applications down the callstack.
+/// On Linux this requires the backend to grab the devices, see `LinuxBackend::grab`.
 pub enum InhibitEvent {
     Yes,
     Maybe(Arc<dyn Fn() -> InhibitEvent + Send + Sync>),
@@ -145,7 +222,6 @@ pub enum InhibitEvent {
 }
 
 impl InhibitEvent {
-    #[cfg(target_os = "windows")]
     fn should_inhibit(&self) -> bool {
         match self {
             InhibitEvent::Yes => true,
@@ -159,6 +235,30 @@ impl InhibitEvent {
     pub fn maybe(f: impl Fn() -> InhibitEvent + Send + Sync + 'static) -> Self {
         InhibitEvent::Maybe(Arc::new(f))
     }
+
+    /// Combines inhibits of multiple actions bound to the same event, inhibits if any of them does.
+    pub(crate) fn any(inhibits: Vec<InhibitEvent>) -> Self {
+        let mut inhibits: Vec<InhibitEvent> = inhibits
+            .into_iter()
+            .filter(|inhibit| !matches!(inhibit, InhibitEvent::No))
+            .collect();
+        if inhibits.len() <= 1 {
+            inhibits.pop().unwrap_or(InhibitEvent::No)
+        } else if inhibits
+            .iter()
+            .any(|inhibit| matches!(inhibit, InhibitEvent::Yes))
+        {
+            InhibitEvent::Yes
+        } else {
+            InhibitEvent::maybe(move || {
+                if inhibits.iter().any(InhibitEvent::should_inhibit) {
+                    InhibitEvent::Yes
+                } else {
+                    InhibitEvent::No
+                }
+            })
+        }
+    }
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
@@ -181,7 +281,7 @@ pub struct Action {
     /// on which thread those are invoked.
     pub callback: Box<dyn Fn(Event, State) + Send + Sync + 'static>,
     /// Whether to inhibit the event propagation to further applications down the call stack.
-    /// This only works on windows.
+    /// On Linux this only works with the grabbing backend, see `LinuxBackend::grab`.
     /// Note that for now the 'release' event cannot be inhibited.
     pub inhibit: InhibitEvent,
     /// This is the recommended mode, to 'defer' this causes every callback to be spawned on a new thread.
@@ -304,113 +404,310 @@ impl Action {
 }
 
 /// Install any key handler that will be invoked on any key presses.
+/// Multiple handlers can be installed, the bind is removed once the returned handle is dropped.
 /// ```
 /// use mki::*;
 ///
 /// fn install_global_handler() {
-///   bind_any_key(Action::handle_kb(|(key)| println!("Some key pressed: {:?}", key)));
+///   bind_any_key(Action::handle_kb(|(key)| println!("Some key pressed: {:?}", key))).forget();
 /// }
 /// ```
-pub fn bind_any_key(action: Action) {
-    *registry().any_key_callback.lock().unwrap() = Some(Arc::new(action))
+pub fn bind_any_key(action: Action) -> BindHandle {
+    started_context().bind_any_key(action)
 }
 
 /// Install any key handler that will be invoked on specified key presses.
+/// Multiple handlers can be bound to the same key, they are invoked in the order they were bound.
+/// The bind is removed once the returned handle is dropped.
 ///```
 /// use mki::*;
 ///
 /// fn bind_some_key() {
-///   bind_key(Keyboard::B, Action::handle_kb(|(key)| println!("B Pressed")));
+///   bind_key(Keyboard::B, Action::handle_kb(|(key)| println!("B Pressed"))).forget();
 /// }
 /// ```
-pub fn bind_key(key: Keyboard, action: Action) {
-    registry()
-        .key_callbacks
-        .lock()
-        .unwrap()
-        .insert(key, Arc::new(action));
+pub fn bind_key(key: Keyboard, action: Action) -> BindHandle {
+    started_context().bind_key(key, action)
 }
 
-/// Removes global key handler.
+/// Removes all the global key handlers.
 ///```
 /// use mki::*;
 ///
 /// fn remove_global_handler() {
-///   bind_any_key(Action::handle_kb(|(key)| println!("Some key pressed: {:?}", key)));
+///   bind_any_key(Action::handle_kb(|(key)| println!("Some key pressed: {:?}", key))).forget();
 ///   remove_any_key_bind();
 /// }
 /// ```
 pub fn remove_any_key_bind() {
-    *registry().any_key_callback.lock().unwrap() = None;
+    started_context().remove_any_key_bind()
 }
 
-/// Removes specific key bind.
+/// Removes all the binds of a specific key.
 pub fn remove_key_bind(key: Keyboard) {
-    registry().key_callbacks.lock().unwrap().remove(&key);
+    started_context().remove_key_bind(key)
 }
 
 /// Same as `bind_any_key` but for mouse buttons.
-pub fn bind_any_button(action: Action) {
-    *registry().any_button_callback.lock().unwrap() = Some(Arc::new(action))
+pub fn bind_any_button(action: Action) -> BindHandle {
+    started_context().bind_any_button(action)
 }
 
 /// Same as `bind_key` but for mouse buttons.
-pub fn bind_button(button: Mouse, action: Action) {
-    registry()
-        .button_callbacks
-        .lock()
-        .unwrap()
-        .insert(button, Arc::new(action));
+pub fn bind_button(button: Mouse, action: Action) -> BindHandle {
+    started_context().bind_button(button, action)
+}
+
+/// Install a handler that will be invoked on a new thread with the clicks whenever the wheel rotates,
+/// positive `dy` is up, positive `dx` is right. The bind is removed once the returned handle is dropped.
+/// `Mouse::ScrollUp` and the like binds are still clicked once per wheel click afterwards.
+/// Rotations scrolled by this library are not passed to the handler, so it can scroll itself.
+///```
+/// use mki::*;
+///
+/// fn print_wheel() {
+///   bind_scroll(InhibitEvent::No, |dx, dy| println!("Wheel rotated by: {} {}", dx, dy)).forget();
+/// }
+/// ```
+pub fn bind_scroll(
+    inhibit: InhibitEvent,
+    handler: impl Fn(i32, i32) + Send + Sync + 'static,
+) -> BindHandle {
+    started_context().bind_scroll(inhibit, handler)
+}
+
+/// Invokes the handler with the gestures of the key or button, see `Gesture` and `Action::gesture`.
+/// The bind is removed once the returned handle is dropped.
+/// Gestures are reported on a new thread, except the ones resolved by pressing another key or button,
+/// these are reported on the listening thread before that press is passed on.
+/// On Linux a key pressed from there thus applies to the other key,
+/// on Windows inject from a new thread instead, the other key may then be passed on first.
+/// ```
+/// use mki::*;
+///
+/// fn caps_lock_escape_or_control() {
+///   let options = GestureOptions::default()
+///     .hold_on_other_key(true)
+///     .wait_for_double_tap(true)
+///     .inhibit(true);
+///   bind_gesture(Event::Keyboard(Keyboard::CapsLock), options, |_, gesture| match gesture {
+///     Gesture::Tap => Keyboard::Escape.click(),
+///     Gesture::DoubleTap => println!("Double tapped"),
+///     Gesture::LongPress => Keyboard::LeftControl.press(),
+///     Gesture::Hold(_) => Keyboard::LeftControl.release(),
+///   }).forget();
+/// }
+/// ```
+pub fn bind_gesture(
+    event: Event,
+    options: GestureOptions,
+    handler: impl Fn(Event, Gesture) + Send + Sync + 'static,
+) -> BindHandle {
+    started_context().bind_gesture(event, options, handler)
 }
 
 /// Same as `remove_any_key_bind` but for mouse buttons.
 pub fn remove_any_button_bind() {
-    *registry().any_button_callback.lock().unwrap() = None;
+    started_context().remove_any_button_bind()
 }
 
 /// Same as `remove_key_bind` but for mouse buttons.
 pub fn remove_button_bind(button: Mouse) {
-    registry().button_callbacks.lock().unwrap().remove(&button);
+    started_context().remove_button_bind(button)
 }
 
 /// Allows for registering an action that will be triggered when sequence of buttons is pressed.
 /// callback will be invoked whenever last key of the sequence is pressed.
+/// Accepts a `Hotkey` for modifiers matching either side, any order or extra held keys.
+/// Callbacks of the matching hotkeys are invoked one after another on a new thread, in order they were registered.
 /// ```
 /// use mki::*;
 ///
 /// fn register() {
-///   register_hotkey(&[Keyboard::LeftControl, Keyboard::B], || println!("CTRL+B pressed"));
+///   register_hotkey(&[Keyboard::LeftControl, Keyboard::B], || println!("CTRL+B pressed")).forget();
+///   let either_ctrl = Hotkey::new(Keyboard::B).modifier(Modifier::Ctrl);
+///   register_hotkey(either_ctrl, || println!("Left or right CTRL+B pressed")).forget();
+///   let keys: Vec<Keyboard> = vec![Keyboard::LeftAlt, Keyboard::B];
+///   register_hotkey(keys.as_slice(), || println!("ALT+B pressed")).forget();
+/// }
+/// ```
+///
+/// # Panics
+///
+/// If given an empty slice or vector of keys.
+pub fn register_hotkey(
+    hotkey: impl Into<Hotkey>,
+    callback: impl Fn() + Send + Sync + 'static,
+) -> BindHandle {
+    started_context().register_hotkey(hotkey, callback)
+}
+
+/// Registers an action triggered by successive hotkeys, e.g. `Ctrl+K Ctrl+C`.
+/// Callback is invoked on a new thread once the last stroke is pressed within the timeout.
+/// Chords completed by a stroke win over the longer ones sharing the prefix, hotkeys fire independently.
+/// ```
+/// use mki::*;
+///
+/// fn comment() {
+///   let chords: Chords = "Ctrl+K Ctrl+C".parse().unwrap();
+///   register_chords(chords, || println!("Commented")).forget();
+///   on_chord_aborted(|strokes| println!("Not a chord: {:?}", strokes));
+/// }
+/// ```
+pub fn register_chords(chords: Chords, callback: impl Fn() + Send + Sync + 'static) -> BindHandle {
+    started_context().register_chords(chords, callback)
+}
+
+/// Registers an action triggered by the leader key followed by the given keys, vim style.
+/// Callback is invoked on a new thread once the last key is pressed within the timeout.
+/// ```
+/// use mki::*;
+///
+/// fn git_status() {
+///   let leader = Leader::new(Keyboard::CapsLock, &[Keyboard::G, Keyboard::S]).suppress(true);
+///   register_leader(leader, || println!("git status")).forget();
+/// }
+/// ```
+pub fn register_leader(leader: Leader, callback: impl Fn() + Send + Sync + 'static) -> BindHandle {
+    started_context().register_leader(leader, callback)
+}
+
+/// Binds the key to activate the layer, the bind itself belongs to the base layer.
+/// Presses of the key are inhibited, on Linux see `LinuxBackend::grab`.
+/// While a layer is active its binds and hotkeys take precedence over those of the layers below,
+/// keys and buttons it does not bind fall through to them.
+/// ```
+/// use mki::*;
+///
+/// fn navigation_layer() {
+///   layer_key(Keyboard::CapsLock, "nav", LayerMode::Momentary).forget();
+///   bind_key_in("nav", Keyboard::H, Action::sequencing_kb(|_| Keyboard::Left.click())).forget();
+/// }
+/// ```
+pub fn layer_key(key: Keyboard, layer: &str, mode: LayerMode) -> BindHandle {
+    started_context().layer_key(key, layer, mode)
+}
+
+/// Version of `bind_key` binding only while the layer is active.
+pub fn bind_key_in(layer: &str, key: Keyboard, action: Action) -> BindHandle {
+    started_context().bind_key_in(layer, key, action)
+}
+
+/// Version of `bind_button` binding only while the layer is active.
+pub fn bind_button_in(layer: &str, button: Mouse, action: Action) -> BindHandle {
+    started_context().bind_button_in(layer, button, action)
+}
+
+/// Version of `register_hotkey` registering only while the layer is active.
+pub fn register_hotkey_in(
+    layer: &str,
+    hotkey: impl Into<Hotkey>,
+    handler: impl Fn() + Send + Sync + 'static,
+) -> BindHandle {
+    started_context().register_hotkey_in(layer, hotkey, handler)
+}
+
+/// Activates the layer on top of the active ones.
+pub fn push_layer(layer: &str) {
+    started_context().push_layer(layer)
+}
+
+/// Deactivates the topmost occurrence of the layer.
+pub fn pop_layer(layer: &str) {
+    started_context().pop_layer(layer)
+}
+
+/// Deactivates the layer if active, activates it otherwise.
+pub fn toggle_layer(layer: &str) {
+    started_context().toggle_layer(layer)
+}
+
+/// Activates the layer in place of all the active ones but the base one.
+pub fn switch_layer(layer: &str) {
+    started_context().switch_layer(layer)
+}
+
+/// Topmost active layer, `BASE_LAYER` if no other is active.
+pub fn active_layer() -> String {
+    started_context().active_layer()
+}
+
+/// Active layers top first, ending with `BASE_LAYER`.
+pub fn active_layers() -> Vec<String> {
+    started_context().active_layers()
+}
+
+/// Strokes typed so far of the chords waiting for their next stroke, empty if none are pending.
+pub fn pending_chord() -> Vec<Hotkey> {
+    started_context().pending_chord()
+}
+
+/// Invoked with the typed strokes whenever pending chords are aborted,
+/// either by the timeout or by a stroke that none of them continues with.
+pub fn on_chord_aborted(f: impl Fn(Vec<Hotkey>) + Send + Sync + 'static) {
+    started_context().on_chord_aborted(f)
+}
+
+/// Makes `from` behave as `to`, the press and release of `from` are inhibited
+/// and replaced by the press and release of `to`, repeats included.
+/// Requires event inhibition, on Linux see `LinuxBackend::grab`.
+/// ```
+/// use mki::*;
+///
+/// fn remap_caps_lock() {
+///   remap(Keyboard::CapsLock, Keyboard::Escape).forget();
+/// }
+/// ```
+pub fn remap(from: Keyboard, to: Keyboard) -> BindHandle {
+    started_context().remap(from, to)
+}
+
+/// Version of `remap` for combinations, the last key of `from` triggers the remap
+/// while the rest of the keys are held down, in any order.
+/// Keys of `from` that are held down and are not part of `to` are released for the duration
+/// of the remap and pressed back afterwards, so that `to` is not altered by those.
+/// The most specific combination takes precedence when multiple remaps share the last key.
+/// Keys injected by this library are never remapped.
+/// ```
+/// use mki::*;
+///
+/// fn remap_combo_backspace() {
+///   remap_combo(&[Keyboard::LeftControl, Keyboard::H], &[Keyboard::BackSpace]).forget();
 /// }
 /// ```
-pub fn register_hotkey(sequence: &[Keyboard], callback: impl Fn() + Send + Sync + 'static) {
-    registry().register_hotkey(sequence, callback);
+pub fn remap_combo(from: &[Keyboard], to: &[Keyboard]) -> BindHandle {
+    started_context().remap_combo(from, to)
 }
 
 /// Returns whether given key sequence is currently pressed down, this may be a single key.
 pub fn are_pressed(sequence: &[Keyboard]) -> bool {
-    registry().are_pressed(sequence)
+    started_context().are_pressed(sequence)
 }
 
 /// Allows storing some kind of state within the library,
 /// Generally not that useful but allows for some more complicated logic using yaml load.
 pub fn set_state(key: &str, value: &str) {
-    registry().set_state(key, value)
+    started_context().set_state(key, value)
 }
 
 /// Returns the state, it has to be set beforehand with the set otherwise will be returned empty.
 pub fn get_state(key: &str) -> Option<String> {
-    registry().get_state(key)
+    started_context().get_state(key)
 }
 
-/// Unregisters hotkey, a original sequence has to be passed as parameter..
-pub fn unregister_hotkey(sequence: &[Keyboard]) {
-    registry().unregister_hotkey(sequence);
+/// Unregisters all the hotkeys equal to the given one, a original sequence has to be passed as parameter..
+///
+/// # Panics
+///
+/// If given an empty slice or vector of keys.
+pub fn unregister_hotkey(hotkey: impl Into<Hotkey>) {
+    started_context().unregister_hotkey(hotkey)
 }
 
 pub fn enable_debug() {
-    registry().enable_debug();
+    started_context().enable_debug()
 }
 
 pub fn print_pressed_state() {
-    registry().print_pressed_state();
+    started_context().print_pressed_state()
 }
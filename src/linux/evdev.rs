@@ -0,0 +1,367 @@
+use crate::keyboard_mouse::{
+    forward_motion, kb_code_to_key, mimpl, mouse_code_to_key, DEVICE_NAME,
+};
+use crate::linux::{eviocgrab, Observer};
+use crate::{InhibitEvent, Listener, MultiClick};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::unistd::close;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::mem::{size_of, size_of_val};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+const INPUT_DIR: &str = "/dev/input";
+
+// From linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const KEY_A: u16 = 30;
+const BTN_MISC: u16 = 0x100;
+const BTN_LEFT: u16 = 0x110;
+const KEY_OK: u16 = 0x160;
+const KEY_CNT: usize = 0x300;
+
+// From linux/input.h
+nix::ioctl_read!(eviocgid, b'E', 0x02, libc::input_id);
+nix::ioctl_read_buf!(eviocgname, b'E', 0x06, u8);
+nix::ioctl_read_buf!(eviocgbit_types, b'E', 0x20, u8);
+nix::ioctl_read_buf!(eviocgbit_keys, b'E', 0x21, u8);
+
+/// Which devices `InputSource::Evdev` reads, all of them unless a rule is added.
+/// A device is read when it matches any of the rules, events injected by this library are always seen.
+/// Touchpads, touchscreens, tablets and joysticks report absolute positions and are skipped,
+/// `InputSource::Libinput` reads those.
+/// ```
+/// use mki::*;
+///
+/// fn kiosk() {
+///   let filter = DeviceFilter::all()
+///     .name("AT Translated Set 2 keyboard")
+///     .id(0x046d, 0xc52b)
+///     .path("/dev/input/by-id/usb-Barcode_Scanner-event-kbd");
+///   set_backend(LinuxBackend::evdev(filter).grab(true));
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeviceFilter {
+    names: Vec<String>,
+    ids: Vec<(u16, u16)>,
+    paths: Vec<PathBuf>,
+}
+
+impl DeviceFilter {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Devices whose name contains the text.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    /// Devices with the vendor and product id, as shown by `lsusb`.
+    pub fn id(mut self, vendor: u16, product: u16) -> Self {
+        self.ids.push((vendor, product));
+        self
+    }
+
+    /// Device at the path, symlinks such as the ones in `/dev/input/by-id` are followed.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    fn matches(&self, path: &Path, name: &str, id: (u16, u16)) -> bool {
+        (self.names.is_empty() && self.ids.is_empty() && self.paths.is_empty())
+            || self.names.iter().any(|part| name.contains(part.as_str()))
+            || self.ids.contains(&id)
+            || self.paths.iter().any(|filtered| {
+                filtered.canonicalize().unwrap_or_else(|_| filtered.clone()) == path
+            })
+    }
+}
+
+pub(crate) fn process_events(
+    listener: Listener,
+    grab: bool,
+    multi_click: MultiClick,
+    filter: &DeviceFilter,
+) {
+    let mut observer = Observer::new(listener, grab, multi_click);
+    // Watched before listing the devices so that none is missed in between.
+    // Permissions are set by udev once the node is created, if there is one.
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK).and_then(|inotify| {
+        inotify.add_watch(
+            INPUT_DIR,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB,
+        )?;
+        Ok(inotify)
+    });
+    if let Err(e) = inotify {
+        eprintln!(
+            "mki: devices plugged in later are not noticed, inotify failed: {}",
+            e
+        );
+    }
+    let mut devices: Vec<EvdevDevice> = match std::fs::read_dir(INPUT_DIR) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| is_event_node(&entry.file_name().to_string_lossy()))
+            .filter_map(|entry| {
+                EvdevDevice::open(&entry.path(), filter, grab)
+                    .map_err(|e| eprintln!("mki: unable to read {}: {}", entry.path().display(), e))
+                    .ok()
+                    .flatten()
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("mki: unable to list {}: {}", INPUT_DIR, e);
+            Vec::new()
+        }
+    };
+    while observer.listener.is_listening() {
+        let mut fds: Vec<PollFd> = devices
+            .iter()
+            .map(|device| PollFd::new(device.file.as_raw_fd(), PollFlags::POLLIN))
+            .chain(
+                inotify
+                    .iter()
+                    .map(|inotify| PollFd::new(inotify.as_raw_fd(), PollFlags::POLLIN)),
+            )
+            .collect();
+        // Timeout so that the shutdown of the context is noticed.
+        if poll(&mut fds, 100).is_err() {
+            continue;
+        }
+        // Before the new nodes are handled, so that one re-created at the same path is opened again.
+        devices.retain_mut(|device| device.read(&mut observer));
+        if let Ok(inotify) = inotify {
+            for event in inotify.read_events().unwrap_or_default() {
+                let path = match event.name {
+                    Some(name) if is_event_node(&name.to_string_lossy()) => {
+                        Path::new(INPUT_DIR).join(name)
+                    }
+                    _ => continue,
+                };
+                if devices.iter().all(|device| device.path != path) {
+                    // Not readable yet, retried once udev changes the permissions.
+                    if let Ok(Some(device)) = EvdevDevice::open(&path, filter, grab) {
+                        devices.push(device);
+                    }
+                }
+            }
+        }
+    }
+    if let Ok(inotify) = inotify {
+        let _ = close(inotify.as_raw_fd());
+    }
+}
+
+fn is_event_node(name: &str) -> bool {
+    name.strip_prefix("event")
+        .is_some_and(|number| number.parse::<u32>().is_ok())
+}
+
+fn has_bit(bits: &[u8], bit: u16) -> bool {
+    bits.get(bit as usize / 8)
+        .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// Opened `/dev/input/event*`, closing it releases the grab as well.
+struct EvdevDevice {
+    path: PathBuf,
+    file: File,
+    /// Our own uinput device, its events were either injected by this library or re-emitted while grabbing.
+    injected: bool,
+    /// Relative motion since the last report.
+    motion: (i32, i32),
+}
+
+impl EvdevDevice {
+    /// None if the device is filtered out, has neither keys nor relative axes
+    /// or reports absolute positions without being a keyboard.
+    fn open(path: &Path, filter: &DeviceFilter, grab: bool) -> io::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)?;
+        let fd = file.as_raw_fd();
+        let mut name = [0u8; 256];
+        let mut id: libc::input_id = unsafe { std::mem::zeroed() };
+        let mut types = [0u8; 4];
+        let mut keys = [0u8; KEY_CNT / 8];
+        unsafe {
+            eviocgname(fd, &mut name)?;
+            eviocgid(fd, &mut id)?;
+            eviocgbit_types(fd, &mut types)?;
+            eviocgbit_keys(fd, &mut keys)?;
+        }
+        let name = String::from_utf8_lossy(name.split(|c| *c == 0).next().unwrap_or_default());
+        let injected = name == DEVICE_NAME;
+        if !injected && !filter.matches(path, &name, (id.vendor, id.product)) {
+            return Ok(None);
+        }
+        if !has_bit(&types, EV_KEY) && !has_bit(&types, EV_REL) {
+            return Ok(None);
+        }
+        // Absolute positions are not turned into motion, keyboards are kept for their keys.
+        if has_bit(&types, EV_ABS) && !has_bit(&keys, KEY_A) {
+            return Ok(None);
+        }
+        // Keyboards and mice, devices reporting absolute positions cannot be re-emitted.
+        let grabbable = (has_bit(&keys, KEY_A) || has_bit(&keys, BTN_LEFT))
+            && !has_bit(&types, EV_ABS)
+            && !injected;
+        if grab && grabbable {
+            if let Err(e) = unsafe { eviocgrab(fd, 1) } {
+                eprintln!("mki: unable to grab {}: {}", name, e);
+            }
+        }
+        Ok(Some(EvdevDevice {
+            path: path.to_path_buf(),
+            file,
+            injected,
+            motion: (0, 0),
+        }))
+    }
+
+    /// Handles the pending events, false once the device is gone.
+    fn read(&mut self, observer: &mut Observer) -> bool {
+        let mut events: [libc::input_event; 64] = unsafe { std::mem::zeroed() };
+        loop {
+            let bytes = unsafe {
+                std::slice::from_raw_parts_mut(events.as_mut_ptr() as *mut u8, size_of_val(&events))
+            };
+            match self.file.read(bytes) {
+                Ok(0) => return false,
+                Ok(read) => {
+                    for event in &events[..read / size_of::<libc::input_event>()] {
+                        self.handle(event, observer);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                // ENODEV once unplugged.
+                Err(_) => return false,
+            }
+        }
+    }
+
+    fn handle(&mut self, event: &libc::input_event, observer: &mut Observer) {
+        let time_usec = event.time.tv_sec as u64 * 1_000_000 + event.time.tv_usec as u64;
+        match (event.type_, event.code) {
+            (EV_KEY, code) => self.key(code, event.value, time_usec, observer),
+            (EV_REL, REL_X) => self.motion.0 += event.value,
+            (EV_REL, REL_Y) => self.motion.1 += event.value,
+            (EV_REL, REL_HWHEEL) => self.scrolled(event.value, 0, observer),
+            (EV_REL, REL_WHEEL) => self.scrolled(0, event.value, observer),
+            (EV_SYN, SYN_REPORT) => self.moved(observer),
+            _ => {}
+        }
+    }
+
+    fn key(&self, code: u16, value: i32, time_usec: u64, observer: &mut Observer) {
+        let pressed = match value {
+            0 => false,
+            1 => true,
+            // Repeat, libinput does not report those either.
+            _ => return,
+        };
+        let code = u32::from(code);
+        let is_button = (BTN_MISC..KEY_OK).contains(&(code as u16));
+        if self.injected {
+            if observer.is_echo(code, pressed) {
+                return;
+            }
+            match (mouse_code_to_key(code), pressed) {
+                (Some(button), true) => observer.button_down(button, time_usec),
+                (Some(button), false) => observer.listener.button_up(button),
+                (None, _) if is_button => InhibitEvent::No,
+                (None, true) => observer.listener.injected_key_down(kb_code_to_key(code)),
+                (None, false) => observer.listener.injected_key_up(kb_code_to_key(code)),
+            };
+            return;
+        }
+        if pressed {
+            let inhibit = match mouse_code_to_key(code) {
+                Some(button) => observer.button_down(button, time_usec),
+                None if is_button => InhibitEvent::No,
+                None => observer.listener.key_down(kb_code_to_key(code)),
+            };
+            observer.forward_down(code, inhibit);
+        } else {
+            match mouse_code_to_key(code) {
+                Some(button) => observer.listener.button_up(button),
+                None if is_button => InhibitEvent::No,
+                None => observer.listener.key_up(kb_code_to_key(code)),
+            };
+            observer.forward_up(code);
+        }
+    }
+
+    fn moved(&mut self, observer: &mut Observer) {
+        let (dx, dy) = std::mem::take(&mut self.motion);
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        if observer.grab && !self.injected {
+            forward_motion(dx, dy);
+        }
+        observer.moved(dx.into(), dy.into(), self.injected);
+    }
+
+    fn scrolled(&self, dx: i32, dy: i32, observer: &mut Observer) {
+        if self.injected {
+            // Re-emitted wheel cannot be told apart from the injected one, it was seen already.
+            if !observer.grab {
+                observer.listener.injected_scrolled(dx, dy);
+            }
+            return;
+        }
+        let inhibit = observer.listener.scrolled(dx, dy);
+        if observer.grab && !inhibit.should_inhibit() {
+            mimpl::scroll(dx, dy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::linux::evdev::{has_bit, is_event_node};
+    use crate::DeviceFilter;
+    use std::path::Path;
+
+    #[test]
+    fn device_filter() {
+        let path = Path::new("/dev/input/event3");
+        assert!(DeviceFilter::all().matches(path, "Keyboard", (1, 2)));
+        let filter = DeviceFilter::all().name("Keyboard").id(0x046d, 0xc52b);
+        assert!(filter.matches(path, "AT Translated Set 2 Keyboard", (1, 2)));
+        assert!(filter.matches(path, "Mouse", (0x046d, 0xc52b)));
+        assert!(!filter.matches(path, "Mouse", (0x046d, 0xc52c)));
+        assert!(DeviceFilter::all()
+            .path(path)
+            .matches(path, "Mouse", (1, 2)));
+        assert!(!DeviceFilter::all()
+            .path("/dev/input/event4")
+            .matches(path, "Mouse", (1, 2)));
+
+        assert!(is_event_node("event12"));
+        assert!(!is_event_node("mice"));
+        assert!(!is_event_node("event"));
+        assert!(has_bit(&[0, 0b100], 10));
+        assert!(!has_bit(&[0, 0b100], 11));
+        assert!(!has_bit(&[0], 30));
+    }
+}
@@ -1,12 +1,19 @@
-use crate::{Keyboard, Mouse};
+use crate::keyboard::KEYS;
+use crate::{Error, Keyboard, Level, Mouse};
+use std::io;
 use std::ptr;
 use std::sync::atomic::{AtomicPtr, Ordering};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use uinput::event::keyboard::Key;
+use uinput::event::keyboard::{Key, KeyPad, Misc};
+use uinput::event::relative::{Position, Wheel};
 use uinput::event::Code;
 use x11::xlib;
 
+/// Name of the uinput device everything is injected through.
+pub(crate) const DEVICE_NAME: &str = "mki";
+const EV_KEY: i32 = 0x01;
+
 enum KeybdAction {
     Press,
     Release,
@@ -14,23 +21,99 @@ enum KeybdAction {
 }
 
 pub(crate) mod kimpl {
-    use crate::keyboard_mouse::{send_key_stroke, with_display, KeybdAction};
-    use crate::Keyboard;
+    use crate::keyboard_mouse::{
+        keysym, report, send_key_stroke, try_with_display, KeybdAction, KeyboardMapping,
+    };
+    use crate::{Error, Keyboard, Level, UnicodeInput};
     use std::mem::MaybeUninit;
-    use x11::xlib;
+    use std::str::FromStr;
+    use std::thread;
+    use std::time::Duration;
+    use x11::{xlib, xtest};
 
     pub(crate) fn press(key: Keyboard) {
-        send_key_stroke(KeybdAction::Press, key)
+        report(try_press(key))
     }
 
     pub(crate) fn release(key: Keyboard) {
-        send_key_stroke(KeybdAction::Release, key)
+        report(try_release(key))
     }
 
     pub(crate) fn click(key: Keyboard) {
+        report(try_click(key))
+    }
+
+    pub(crate) fn try_press(key: Keyboard) -> Result<(), Error> {
+        send_key_stroke(KeybdAction::Press, key)
+    }
+
+    pub(crate) fn try_release(key: Keyboard) -> Result<(), Error> {
+        send_key_stroke(KeybdAction::Release, key)
+    }
+
+    pub(crate) fn try_click(key: Keyboard) -> Result<(), Error> {
         send_key_stroke(KeybdAction::Click, key)
     }
 
+    pub(crate) fn type_char(c: char, unicode: UnicodeInput) {
+        match unicode {
+            UnicodeInput::Keysym => report(try_type_keysym(keysym(c))),
+            UnicodeInput::CtrlShiftU => {
+                use Keyboard::*;
+                press(LeftControl);
+                press(LeftShift);
+                click(U);
+                release(LeftShift);
+                release(LeftControl);
+                for digit in format!("{:x}", c as u32).chars() {
+                    click_char(digit);
+                }
+                click(Space);
+            }
+        }
+    }
+
+    /// Clicks the key producing given character on the active layout, holding its modifiers.
+    /// Falls back to the US key when the layout lacks the character.
+    fn click_char(c: char) {
+        let (key, level) = crate::layout().key(c).unwrap_or_else(|| {
+            let key = Keyboard::from_str(&c.to_string()).expect("hex digit is a key");
+            (key, Level::Base)
+        });
+        for modifier in level.modifiers() {
+            press(*modifier);
+        }
+        click(key);
+        for modifier in level.modifiers().iter().rev() {
+            release(*modifier);
+        }
+    }
+
+    fn try_type_keysym(keysym: xlib::KeySym) -> Result<(), Error> {
+        try_with_display(|display| unsafe {
+            let mapping = KeyboardMapping::new(display).ok_or_else(|| {
+                Error::DeviceUnavailable("cannot read the X keyboard mapping".into())
+            })?;
+            let spare = (mapping.min..=mapping.max).rev().find(|keycode| {
+                (0..mapping.per_keycode).all(|level| mapping.keysym(*keycode, level) == 0)
+            });
+            let keycode = spare.ok_or_else(|| {
+                Error::Unsupported(format!("no spare X keycode to type keysym {:#x}", keysym))
+            })?;
+            let mut keysyms = [keysym, keysym];
+            xlib::XChangeKeyboardMapping(display, keycode, 2, keysyms.as_mut_ptr(), 1);
+            xlib::XSync(display, xlib::False);
+            xtest::XTestFakeKeyEvent(display, keycode as u32, xlib::True, 0);
+            xtest::XTestFakeKeyEvent(display, keycode as u32, xlib::False, 0);
+            xlib::XSync(display, xlib::False);
+            // The client looks the keysym up once the event arrives, give it some time before restoring.
+            thread::sleep(Duration::from_millis(20));
+            let mut keysyms = [0, 0];
+            xlib::XChangeKeyboardMapping(display, keycode, 2, keysyms.as_mut_ptr(), 1);
+            Ok(())
+        })?
+    }
+
     pub(crate) fn is_toggled(key: Keyboard) -> bool {
         if let Some(key) = match key {
             Keyboard::ScrollLock => Some(4),
@@ -39,55 +122,226 @@ pub(crate) mod kimpl {
             _ => None,
         } {
             let mut state: xlib::XKeyboardState = unsafe { MaybeUninit::zeroed().assume_init() };
-            with_display(|display| unsafe {
+            let queried = try_with_display(|display| unsafe {
                 xlib::XGetKeyboardControl(display, &mut state);
             });
-            state.led_mask & key != 0
+            report(queried.clone());
+            queried.is_ok() && state.led_mask & key != 0
         } else {
             false
         }
     }
 }
 
-fn send_key_stroke(action: KeybdAction, key: Keyboard) {
-    let mut device = device();
-    if let Some(key) = key_to_event(key) {
+fn send_key_stroke(action: KeybdAction, key: Keyboard) -> Result<(), Error> {
+    let key = key_to_event(key).ok_or(Error::UnmappedKey(key))?;
+    with_device(|device| {
         match action {
-            KeybdAction::Press => device.press(&key).unwrap(),
-            KeybdAction::Release => device.release(&key).unwrap(),
-            KeybdAction::Click => device.click(&key).unwrap(),
+            KeybdAction::Press => device.press(&key)?,
+            KeybdAction::Release => device.release(&key)?,
+            KeybdAction::Click => device.click(&key)?,
+        }
+        device.synchronize()
+    })
+}
+
+fn send_scroll(dx: i32, dy: i32) -> Result<(), Error> {
+    with_device(|device| {
+        if dx != 0 {
+            device.send(Wheel::Horizontal, dx)?;
+        }
+        if dy != 0 {
+            device.send(Wheel::Vertical, dy)?;
+        }
+        device.synchronize()
+    })
+}
+
+/// Re-emits a key or button of a grabbed device as is, codes unknown to `Keyboard` included.
+pub(crate) fn forward_key(code: u32, pressed: bool) {
+    report(with_device(|device| {
+        device.write(EV_KEY, code as i32, pressed as i32)?;
+        device.synchronize()
+    }))
+}
+
+/// Evdev code of the buttons injected through uinput, `None` for Left, Right, Middle and the wheel.
+fn button_code(button: Mouse) -> Option<i32> {
+    use uinput::event::controller::Mouse as IMouse;
+    let code = match button.single() {
+        Mouse::Side => IMouse::Side,
+        Mouse::Extra => IMouse::Extra,
+        Mouse::Forward => IMouse::Forward,
+        Mouse::Back => IMouse::Back,
+        Mouse::Task => IMouse::Task,
+        _ => return None,
+    };
+    Some(code.code())
+}
+
+fn send_button(button: Mouse, pressed: bool) -> Result<(), Error> {
+    let code = match button_code(button) {
+        Some(code) => code,
+        None => return Ok(()),
+    };
+    with_device(|device| {
+        device.write(EV_KEY, code, pressed as i32)?;
+        device.synchronize()
+    })
+}
+
+/// Re-emits the relative motion of a grabbed device.
+pub(crate) fn forward_motion(dx: i32, dy: i32) {
+    report(with_device(|device| {
+        if dx != 0 {
+            device.send(Position::X, dx)?;
         }
+        if dy != 0 {
+            device.send(Position::Y, dy)?;
+        }
+        device.synchronize()
+    }))
+}
+
+/// Failures of the infallible paths are printed, the `try_` versions return these instead.
+pub(crate) fn report(result: Result<(), Error>) {
+    if let Err(e) = result {
+        eprintln!("mki: {}", e);
     }
-    device.synchronize().unwrap();
 }
 
-fn device() -> MutexGuard<'static, uinput::Device> {
+/// Runs `f` with the uinput device, creating it first if needed.
+/// Creation is retried on the next call if it fails, e.g. once the permissions are fixed.
+fn with_device<R>(
+    f: impl FnOnce(&mut uinput::Device) -> Result<R, uinput::Error>,
+) -> Result<R, Error> {
     lazy_static::lazy_static! {
-        static ref DEVICE: Arc<Mutex<uinput::Device>> = {
-            let mut device =
-                uinput::default()
-                .unwrap()
-                .name("mki")
-                .unwrap()
-                .event(uinput::event::Keyboard::All)
-                .unwrap();
-            for v in uinput::event::controller::Mouse::iter_variants() {
-                device = device.event(v).unwrap();
+        static ref DEVICE: Mutex<Option<uinput::Device>> = Mutex::new(None);
+    }
+    let mut device = DEVICE.lock().unwrap();
+    if device.is_none() {
+        *device = Some(create_device()?);
+    }
+    f(device.as_mut().unwrap()).map_err(|e| Error::DeviceUnavailable(e.to_string()))
+}
+
+fn create_device() -> Result<uinput::Device, Error> {
+    // uinput reports every failure alike, opening it first tells the missing permission apart.
+    if let Err(e) = std::fs::OpenOptions::new().write(true).open("/dev/uinput") {
+        return Err(match e.kind() {
+            io::ErrorKind::PermissionDenied => {
+                Error::PermissionDenied(format!("/dev/uinput: {}", e))
             }
-            // This does not seem to work.
-            // device = device.event(Event::Absolute(Absolute::Position(Position::X))).unwrap().min(0).max(100);
-            // device = device.event(Event::Absolute(Absolute::Position(Position::Y))).unwrap().min(0).max(100);
-            let mut device = device.create().unwrap();
-            // Without this there seems to be some inputs gone to hell
-            device.synchronize().unwrap();
-            std::thread::sleep(Duration::from_millis(100));
-            Arc::new(Mutex::new(device))
-        };
+            _ => Error::DeviceUnavailable(format!("/dev/uinput: {}", e)),
+        });
+    }
+    let create = || -> Result<uinput::Device, uinput::Error> {
+        let mut device = uinput::default()?
+            .name(DEVICE_NAME)?
+            .event(uinput::event::Keyboard::All)?;
+        for v in uinput::event::controller::Mouse::iter_variants() {
+            device = device.event(v)?;
+        }
+        device = device.event(Position::X)?;
+        device = device.event(Position::Y)?;
+        device = device.event(Wheel::Vertical)?;
+        device = device.event(Wheel::Horizontal)?;
+        // This does not seem to work.
+        // device = device.event(Event::Absolute(Absolute::Position(Position::X))).unwrap().min(0).max(100);
+        // device = device.event(Event::Absolute(Absolute::Position(Position::Y))).unwrap().min(0).max(100);
+        let mut device = device.create()?;
+        // Without this there seems to be some inputs gone to hell
+        device.synchronize()?;
+        std::thread::sleep(Duration::from_millis(100));
+        Ok(device)
+    };
+    create().map_err(|e| Error::DeviceUnavailable(e.to_string()))
+}
+
+/// Copy of the keysyms the X server maps to the keycodes.
+struct KeyboardMapping {
+    min: i32,
+    max: i32,
+    per_keycode: i32,
+    keysyms: Vec<xlib::KeySym>,
+}
+
+impl KeyboardMapping {
+    unsafe fn new(display: *mut xlib::Display) -> Option<Self> {
+        let (mut min, mut max) = (0, 0);
+        xlib::XDisplayKeycodes(display, &mut min, &mut max);
+        let mut per_keycode = 0;
+        let mapping =
+            xlib::XGetKeyboardMapping(display, min as u8, max - min + 1, &mut per_keycode);
+        if mapping.is_null() {
+            return None;
+        }
+        let len = ((max - min + 1) * per_keycode) as usize;
+        let keysyms = std::slice::from_raw_parts(mapping, len).to_vec();
+        xlib::XFree(mapping as *mut _);
+        Some(KeyboardMapping {
+            min,
+            max,
+            per_keycode,
+            keysyms,
+        })
+    }
+
+    fn keysym(&self, keycode: i32, index: i32) -> xlib::KeySym {
+        if keycode < self.min || keycode > self.max || index >= self.per_keycode {
+            return 0;
+        }
+        self.keysyms[((keycode - self.min) * self.per_keycode + index) as usize]
     }
-    DEVICE.lock().unwrap()
 }
 
-fn with_display<R>(mut f: impl FnMut(*mut xlib::Display) -> R) -> R {
+// Latin-1 keysyms match the code points, the rest is offset, see X11/keysymdef.h
+fn keysym(c: char) -> xlib::KeySym {
+    match c as u32 {
+        code @ 0x20..=0x7e | code @ 0xa0..=0xff => code.into(),
+        code => (0x0100_0000 | code).into(),
+    }
+}
+
+fn keysym_to_char(keysym: xlib::KeySym) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => std::char::from_u32(keysym as u32),
+        0x20ac => Some('€'),
+        0x0100_0100..=0x0110_ffff => std::char::from_u32((keysym - 0x0100_0000) as u32),
+        _ => None,
+    }
+}
+
+/// Characters the X server maps to the keys, see `Layout::from_xkb`.
+pub(crate) fn xkb_chars(
+    keys: impl Iterator<Item = Keyboard>,
+    levels: &[Level],
+) -> Vec<((Keyboard, Level), char)> {
+    let mapping = match try_with_display(|display| unsafe { KeyboardMapping::new(display) }) {
+        Ok(Some(mapping)) => mapping,
+        _ => return Vec::new(),
+    };
+    let mut chars = Vec::new();
+    for key in keys.filter(|key| i32::from(*key) > 0) {
+        // X keycodes are the evdev codes offset by 8.
+        let keycode = i32::from(key) + 8;
+        for level in levels {
+            // Core mapping holds both levels of group 1, then of group 2, then level 3 and 4 of group 1.
+            let index = match level {
+                Level::Base => 0,
+                Level::Shift => 1,
+                Level::AltGr => 4,
+                Level::ShiftAltGr => 5,
+            };
+            if let Some(c) = keysym_to_char(mapping.keysym(keycode, index)) {
+                chars.push(((key, *level), c));
+            }
+        }
+    }
+    chars
+}
+
+fn try_with_display<R>(mut f: impl FnMut(*mut xlib::Display) -> R) -> Result<R, Error> {
     lazy_static::lazy_static! {
         static ref DISPLAY: Arc<Mutex<AtomicPtr<xlib::Display>>> = {
             unsafe {xlib::XInitThreads()};
@@ -97,251 +351,89 @@ fn with_display<R>(mut f: impl FnMut(*mut xlib::Display) -> R) -> R {
     }
     let locked = DISPLAY.lock().unwrap();
     let display: *mut xlib::Display = locked.load(Ordering::Relaxed);
+    if display.is_null() {
+        return Err(Error::NoDisplay);
+    }
     unsafe { xlib::XLockDisplay(display) }
     let r = f(display);
     unsafe {
         xlib::XFlush(display);
         xlib::XUnlockDisplay(display);
     }
-    r
+    Ok(r)
+}
+
+/// uinput event of the key, evdev codes are the same.
+pub fn key_to_event(key: Keyboard) -> Option<uinput::event::Keyboard> {
+    use uinput::event::keyboard::{
+        Attendant, Braille, Camera, Function, InputAssist, Numeric, TouchPad,
+    };
+    use uinput::event::Keyboard as Event;
+    let code = i32::from(key);
+    // Every code the device is created with, see `create_device`.
+    Key::iter_variants()
+        .map(Event::Key)
+        .chain(KeyPad::iter_variants().map(Event::KeyPad))
+        .chain(Misc::iter_variants().map(Event::Misc))
+        .chain(InputAssist::iter_variants().map(Event::InputAssist))
+        .chain(Function::iter_variants().map(Event::Function))
+        .chain(Braille::iter_variants().map(Event::Braille))
+        .chain(Numeric::iter_variants().map(Event::Numeric))
+        .chain(TouchPad::iter_variants().map(Event::TouchPad))
+        .chain(Camera::iter_variants().map(Event::Camera))
+        .chain(Attendant::iter_variants().map(Event::Attendant))
+        .find(|event| event.code() == code)
 }
 
-pub fn key_to_event(key: Keyboard) -> Option<Key> {
-    use Keyboard::*;
-    match key {
-        BackSpace => Some(Key::BackSpace),
-        Tab => Some(Key::Tab),
-        Enter => Some(Key::Enter),
-        Escape => Some(Key::Esc),
-        Space => Some(Key::Space),
-        Home => Some(Key::Home),
-        Left => Some(Key::Left),
-        Up => Some(Key::Up),
-        Right => Some(Key::Right),
-        Down => Some(Key::Down),
-        Insert => Some(Key::Insert),
-        Delete => Some(Key::Delete),
-        Number0 => Some(Key::_0),
-        Number1 => Some(Key::_1),
-        Number2 => Some(Key::_2),
-        Number3 => Some(Key::_3),
-        Number4 => Some(Key::_4),
-        Number5 => Some(Key::_5),
-        Number6 => Some(Key::_6),
-        Number7 => Some(Key::_7),
-        Number8 => Some(Key::_8),
-        Number9 => Some(Key::_9),
-        A => Some(Key::A),
-        B => Some(Key::B),
-        C => Some(Key::C),
-        D => Some(Key::D),
-        E => Some(Key::E),
-        F => Some(Key::F),
-        G => Some(Key::G),
-        H => Some(Key::H),
-        I => Some(Key::I),
-        J => Some(Key::J),
-        K => Some(Key::K),
-        L => Some(Key::L),
-        M => Some(Key::M),
-        N => Some(Key::N),
-        O => Some(Key::O),
-        P => Some(Key::P),
-        Q => Some(Key::Q),
-        R => Some(Key::R),
-        S => Some(Key::S),
-        T => Some(Key::T),
-        U => Some(Key::U),
-        V => Some(Key::V),
-        W => Some(Key::W),
-        X => Some(Key::X),
-        Y => Some(Key::Y),
-        Z => Some(Key::Z),
-        Numpad0 => Some(Key::_0),
-        Numpad1 => Some(Key::_1),
-        Numpad2 => Some(Key::_2),
-        Numpad3 => Some(Key::_3),
-        Numpad4 => Some(Key::_4),
-        Numpad5 => Some(Key::_5),
-        Numpad6 => Some(Key::_6),
-        Numpad7 => Some(Key::_7),
-        Numpad8 => Some(Key::_8),
-        Numpad9 => Some(Key::_9),
-        F1 => Some(Key::F1),
-        F2 => Some(Key::F2),
-        F3 => Some(Key::F3),
-        F4 => Some(Key::F4),
-        F5 => Some(Key::F5),
-        F6 => Some(Key::F6),
-        F7 => Some(Key::F7),
-        F8 => Some(Key::F8),
-        F9 => Some(Key::F9),
-        F10 => Some(Key::F10),
-        NumLock => Some(Key::NumLock),
-        ScrollLock => Some(Key::ScrollLock),
-        CapsLock => Some(Key::CapsLock),
-        LeftShift => Some(Key::LeftShift),
-        RightShift => Some(Key::RightShift),
-        LeftControl => Some(Key::LeftControl),
-        F11 => Some(Key::F11),
-        F12 => Some(Key::F12),
-        F13 => Some(Key::F13),
-        F14 => Some(Key::F14),
-        F15 => Some(Key::F15),
-        F16 => Some(Key::F16),
-        F17 => Some(Key::F17),
-        F18 => Some(Key::F18),
-        F19 => Some(Key::F19),
-        F20 => Some(Key::F20),
-        F21 => Some(Key::F21),
-        F22 => Some(Key::F22),
-        F23 => Some(Key::F23),
-        F24 => Some(Key::F24),
-        RightControl => Some(Key::RightControl),
-        Other(_code) => None,
-        LeftAlt => Some(Key::LeftAlt),
-        RightAlt => Some(Key::RightAlt),
-        PageUp => Some(Key::PageUp),
-        PageDown => Some(Key::PageDown),
-        Print => None,
-        PrintScreen => None,
-        LeftWindows => None,
-        RightWindows => None,
-        Multiply => None,
-        Add => None,
-        Separator => None,
-        Subtract => None,
-        Decimal => None,
-        Divide => None,
-        Comma => Some(Key::Comma),
-        Period => Some(Key::Dot),
-        Slash => Some(Key::Slash),
-        SemiColon => Some(Key::SemiColon),
-        Apostrophe => Some(Key::Apostrophe),
-        LeftBrace => Some(Key::LeftBrace),
-        BackwardSlash => Some(Key::BackSlash),
-        RightBrace => Some(Key::RightBrace),
-        Grave => Some(Key::Grave),
+/// Set 1 scan codes above 0x58 and their evdev codes, the ones below are the evdev codes.
+#[rustfmt::skip]
+const SCANCODES: &[(u8, u16)] = &[
+    (0x59, 117), (0x64, 183), (0x65, 184), (0x66, 185), (0x67, 186), (0x68, 187), (0x69, 188),
+    (0x6a, 189), (0x6b, 190), (0x6c, 191), (0x6d, 192), (0x6e, 193), (0x70, 93), (0x73, 89),
+    (0x76, 194), (0x79, 92), (0x7b, 94), (0x7d, 124), (0x7e, 121),
+];
+
+/// Set 1 scan codes prefixed with 0xe0, without the prefix, and their evdev codes.
+#[rustfmt::skip]
+const EXTENDED_SCANCODES: &[(u8, u16)] = &[
+    (0x10, 165), (0x19, 163), (0x1c, 96), (0x1d, 97), (0x20, 113), (0x21, 140), (0x22, 164),
+    (0x24, 166), (0x2e, 114), (0x30, 115), (0x32, 172), (0x35, 98), (0x37, 99), (0x38, 100),
+    (0x46, 119), (0x47, 102), (0x48, 103), (0x49, 104), (0x4b, 105), (0x4d, 106), (0x4f, 107),
+    (0x50, 108), (0x51, 109), (0x52, 110), (0x53, 111), (0x5b, 125), (0x5c, 126), (0x5d, 127),
+    (0x5e, 116), (0x5f, 142), (0x63, 143), (0x65, 217), (0x66, 156), (0x67, 173), (0x68, 128),
+    (0x69, 159), (0x6a, 158), (0x6b, 157), (0x6c, 155), (0x6d, 226),
+];
+
+fn scancode_to_evdev(code: u32) -> Option<u16> {
+    let find = |table: &[(u8, u16)], scancode: u32| {
+        table
+            .iter()
+            .find(|(known, _)| u32::from(*known) == scancode)
+            .map(|(_, evdev)| *evdev)
+    };
+    match code {
+        1..=0x58 => Some(code as u16),
+        0x59..=0xff => find(SCANCODES, code),
+        0xe000..=0xe0ff => find(EXTENDED_SCANCODES, code & 0xff),
+        _ => None,
     }
 }
 
 impl From<Keyboard> for i32 {
     fn from(key: Keyboard) -> i32 {
-        if let Some(key) = key_to_event(key) {
-            key.code()
-        } else {
-            -1
+        match key {
+            Keyboard::Other(code) => code,
+            Keyboard::Native(code) => code as i32,
+            Keyboard::Scancode(code) => scancode_to_evdev(code).map_or(-1, i32::from),
+            key => key.info().map_or(-1, |info| info.evdev.into()),
         }
     }
 }
 
 pub(crate) fn kb_code_to_key(code: u32) -> Keyboard {
-    use Keyboard::*;
-    match code as i32 {
-        code if Key::BackSpace.code() == code => BackSpace,
-        code if Key::Tab.code() == code => Tab,
-        code if Key::Enter.code() == code => Enter,
-        code if Key::Esc.code() == code => Escape,
-        code if Key::Space.code() == code => Space,
-        code if Key::Home.code() == code => Home,
-        code if Key::Left.code() == code => Left,
-        code if Key::Up.code() == code => Up,
-        code if Key::Right.code() == code => Right,
-        code if Key::Down.code() == code => Down,
-        code if Key::Insert.code() == code => Insert,
-        code if Key::Delete.code() == code => Delete,
-        code if Key::_0.code() == code => Number0,
-        code if Key::_1.code() == code => Number1,
-        code if Key::_2.code() == code => Number2,
-        code if Key::_3.code() == code => Number3,
-        code if Key::_4.code() == code => Number4,
-        code if Key::_5.code() == code => Number5,
-        code if Key::_6.code() == code => Number6,
-        code if Key::_7.code() == code => Number7,
-        code if Key::_8.code() == code => Number8,
-        code if Key::_9.code() == code => Number9,
-        code if Key::A.code() == code => A,
-        code if Key::B.code() == code => B,
-        code if Key::C.code() == code => C,
-        code if Key::D.code() == code => D,
-        code if Key::E.code() == code => E,
-        code if Key::F.code() == code => F,
-        code if Key::G.code() == code => G,
-        code if Key::H.code() == code => H,
-        code if Key::I.code() == code => I,
-        code if Key::J.code() == code => J,
-        code if Key::K.code() == code => K,
-        code if Key::L.code() == code => L,
-        code if Key::M.code() == code => M,
-        code if Key::N.code() == code => N,
-        code if Key::O.code() == code => O,
-        code if Key::P.code() == code => P,
-        code if Key::Q.code() == code => Q,
-        code if Key::R.code() == code => R,
-        code if Key::S.code() == code => S,
-        code if Key::T.code() == code => T,
-        code if Key::U.code() == code => U,
-        code if Key::V.code() == code => V,
-        code if Key::W.code() == code => W,
-        code if Key::X.code() == code => X,
-        code if Key::Y.code() == code => Y,
-        code if Key::Z.code() == code => Z,
-        code if Key::_0.code() == code => Numpad0,
-        code if Key::_1.code() == code => Numpad1,
-        code if Key::_2.code() == code => Numpad2,
-        code if Key::_3.code() == code => Numpad3,
-        code if Key::_4.code() == code => Numpad4,
-        code if Key::_5.code() == code => Numpad5,
-        code if Key::_6.code() == code => Numpad6,
-        code if Key::_7.code() == code => Numpad7,
-        code if Key::_8.code() == code => Numpad8,
-        code if Key::_9.code() == code => Numpad9,
-        code if Key::F1.code() == code => F1,
-        code if Key::F2.code() == code => F2,
-        code if Key::F3.code() == code => F3,
-        code if Key::F4.code() == code => F4,
-        code if Key::F5.code() == code => F5,
-        code if Key::F6.code() == code => F6,
-        code if Key::F7.code() == code => F7,
-        code if Key::F8.code() == code => F8,
-        code if Key::F9.code() == code => F9,
-        code if Key::F10.code() == code => F10,
-        code if Key::NumLock.code() == code => NumLock,
-        code if Key::ScrollLock.code() == code => ScrollLock,
-        code if Key::CapsLock.code() == code => CapsLock,
-        code if Key::LeftShift.code() == code => LeftShift,
-        code if Key::RightShift.code() == code => RightShift,
-        code if Key::LeftControl.code() == code => LeftControl,
-        code if Key::F11.code() == code => F11,
-        code if Key::F12.code() == code => F12,
-        code if Key::F13.code() == code => F13,
-        code if Key::F14.code() == code => F14,
-        code if Key::F15.code() == code => F15,
-        code if Key::F16.code() == code => F16,
-        code if Key::F17.code() == code => F17,
-        code if Key::F18.code() == code => F18,
-        code if Key::F19.code() == code => F19,
-        code if Key::F20.code() == code => F20,
-        code if Key::F21.code() == code => F21,
-        code if Key::F22.code() == code => F22,
-        code if Key::F23.code() == code => F23,
-        code if Key::F24.code() == code => F24,
-        code if Key::RightControl.code() == code => RightControl,
-        code if Key::PageUp.code() == code => PageUp,
-        code if Key::PageDown.code() == code => PageDown,
-        code if Key::Comma.code() == code => Comma,
-        code if Key::Dot.code() == code => Period,
-        code if Key::Slash.code() == code => Slash,
-        code if Key::SemiColon.code() == code => SemiColon,
-        code if Key::Apostrophe.code() == code => Apostrophe,
-        code if Key::LeftBrace.code() == code => LeftBrace,
-        code if Key::BackSlash.code() == code => BackwardSlash,
-        code if Key::RightBrace.code() == code => RightBrace,
-        code if Key::Grave.code() == code => Grave,
-        code => Other(code),
-        // Print, PrintScreen, LeftWin, RightWin, Add, Subtract, Multiply, Divide, Separator, Subtract
-        // Decimal Divide
-    }
+    KEYS.iter()
+        .find(|info| u32::from(info.evdev) == code)
+        .map_or(Keyboard::Native(code), |info| info.key)
 }
 
 pub(crate) fn mouse_code_to_key(code: u32) -> Option<Mouse> {
@@ -360,46 +452,79 @@ pub(crate) fn mouse_code_to_key(code: u32) -> Option<Mouse> {
 }
 
 fn mouse_to_xlib_code(mouse: Mouse) -> Option<u32> {
-    let mapped = match mouse {
+    // Multi clicks are detected by the listener, injecting one presses the button once.
+    let mapped = match mouse.single() {
         Mouse::Left => 1,
         Mouse::Right => 3,
         Mouse::Middle => 2,
-        Mouse::Side => 4,
-        Mouse::Extra => 5,
-        Mouse::Forward | Mouse::Back | Mouse::Task => return None,
+        // These go through uinput.
+        Mouse::Side | Mouse::Extra | Mouse::Forward | Mouse::Back | Mouse::Task => return None,
+        // Scrolling goes through uinput.
+        Mouse::ScrollUp | Mouse::ScrollDown | Mouse::ScrollLeft | Mouse::ScrollRight => {
+            return None
+        }
+        _ => unreachable!("Not a single button"),
     };
     Some(mapped)
 }
 
 pub(crate) mod mimpl {
-    use crate::keyboard_mouse::{mouse_to_xlib_code, with_display};
-    use crate::Mouse;
-    use x11::xlib::{XDefaultScreen, XRootWindow, XWarpPointer};
+    use crate::keyboard_mouse::{
+        mouse_to_xlib_code, report, send_button, send_scroll, try_with_display,
+    };
+    use crate::{Error, Mouse};
+    use x11::xlib::{XDefaultScreen, XQueryPointer, XRootWindow, XWarpPointer};
     use x11::xtest;
 
     pub(crate) fn press(button: Mouse) {
-        if let Some(code) = mouse_to_xlib_code(button) {
-            with_display(|display| {
-                unsafe { xtest::XTestFakeButtonEvent(display, code, 1, 0) };
-            });
-        }
+        report(try_press(button))
     }
 
     pub(crate) fn click(button: Mouse) {
-        press(button);
-        release(button);
+        report(try_click(button))
     }
 
     pub(crate) fn release(button: Mouse) {
+        report(try_release(button))
+    }
+
+    pub(crate) fn try_press(button: Mouse) -> Result<(), Error> {
+        if let Some((dx, dy)) = button.scroll_delta() {
+            send_scroll(dx, dy)
+        } else if let Some(code) = mouse_to_xlib_code(button) {
+            try_with_display(|display| {
+                unsafe { xtest::XTestFakeButtonEvent(display, code, 1, 0) };
+            })
+        } else {
+            send_button(button, true)
+        }
+    }
+
+    pub(crate) fn try_click(button: Mouse) -> Result<(), Error> {
+        try_press(button)?;
+        try_release(button)
+    }
+
+    pub(crate) fn try_release(button: Mouse) -> Result<(), Error> {
         if let Some(code) = mouse_to_xlib_code(button) {
-            with_display(|display| {
+            try_with_display(|display| {
                 unsafe { xtest::XTestFakeButtonEvent(display, code, 0, 0) };
-            });
+            })
+        } else {
+            send_button(button, false)
         }
     }
 
+    pub(crate) fn scroll(dx: i32, dy: i32) {
+        report(send_scroll(dx, dy))
+    }
+
     pub(crate) fn move_to(x: i32, y: i32) {
-        with_display(|display| unsafe {
+        report(try_move_to(x, y))
+    }
+
+    pub(crate) fn try_move_to(x: i32, y: i32) -> Result<(), Error> {
+        try_with_display(|display| unsafe {
             XWarpPointer(
                 display,
                 0,
@@ -411,17 +536,99 @@ pub(crate) mod mimpl {
                 x,
                 y,
             );
-        });
+        })
     }
 
     pub(crate) fn move_by(x: i32, y: i32) {
-        with_display(|display| unsafe {
+        report(try_move_by(x, y))
+    }
+
+    pub(crate) fn try_move_by(x: i32, y: i32) -> Result<(), Error> {
+        try_with_display(|display| unsafe {
             XWarpPointer(display, 0, 0, 0, 0, 0, 0, x, y);
-        });
+        })
     }
 
     pub(crate) fn click_at(x: i32, y: i32, button: Mouse) {
         move_to(x, y);
         click(button);
     }
+
+    pub(crate) fn position() -> (i32, i32) {
+        try_position().unwrap_or_else(|e| {
+            report(Err(e));
+            (0, 0)
+        })
+    }
+
+    pub(crate) fn try_position() -> Result<(i32, i32), Error> {
+        try_with_display(|display| unsafe {
+            let (mut root, mut child) = (0, 0);
+            let (mut x, mut y, mut window_x, mut window_y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            XQueryPointer(
+                display,
+                XRootWindow(display, XDefaultScreen(display)),
+                &mut root,
+                &mut child,
+                &mut x,
+                &mut y,
+                &mut window_x,
+                &mut window_y,
+                &mut mask,
+            );
+            (x, y)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyboard_mouse::{
+        button_code, kb_code_to_key, key_to_event, mouse_code_to_key, mouse_to_xlib_code,
+    };
+    use crate::Keyboard::*;
+    use crate::{Keyboard, Mouse};
+    use uinput::event::Code;
+
+    #[test]
+    fn every_key_round_trips() {
+        for key in Keyboard::all() {
+            let event = key_to_event(key).unwrap_or_else(|| panic!("{} is not mapped", key));
+            assert_eq!(key, kb_code_to_key(event.code() as u32));
+        }
+        assert_eq!(NumpadEnter, kb_code_to_key(96));
+        assert_eq!(Native(0x2ff), kb_code_to_key(0x2ff));
+        // KEY_FN_ESC, no variant but the device has it.
+        let event = key_to_event(Native(0x1d1)).expect("KEY_FN_ESC is injectable");
+        assert_eq!(Native(0x1d1), kb_code_to_key(event.code() as u32));
+        assert_eq!(key_to_event(Escape), key_to_event(Scancode(0x01)));
+        assert_eq!(key_to_event(Up), key_to_event(Scancode(0xe048)));
+        assert_eq!(key_to_event(NumpadEnter), key_to_event(Scancode(0xe01c)));
+        assert_eq!(key_to_event(F13), key_to_event(Scancode(0x64)));
+        // KEY_SLEEP, no variant.
+        assert_eq!(key_to_event(Native(142)), key_to_event(Scancode(0xe05f)));
+        assert!(key_to_event(Native(142)).is_some());
+        assert_eq!(None, key_to_event(Scancode(0xe0ff)));
+    }
+
+    #[test]
+    fn every_button_round_trips() {
+        for button in [
+            Mouse::Side,
+            Mouse::Extra,
+            Mouse::Forward,
+            Mouse::Back,
+            Mouse::Task,
+        ] {
+            assert_eq!(None, mouse_to_xlib_code(button));
+            let code = button_code(button).unwrap_or_else(|| panic!("{} is not mapped", button));
+            assert_eq!(Some(button), mouse_code_to_key(code as u32));
+        }
+        assert_eq!(button_code(Mouse::Side), button_code(Mouse::DoubleSide));
+        for button in [Mouse::Left, Mouse::Right, Mouse::Middle] {
+            assert_eq!(None, button_code(button));
+            assert!(mouse_to_xlib_code(button).is_some());
+        }
+    }
 }
@@ -0,0 +1,279 @@
+use crate::keyboard_mouse::{
+    forward_motion, kb_code_to_key, mimpl, mouse_code_to_key, report, DEVICE_NAME,
+};
+use crate::linux::{eviocgrab, Observer};
+use crate::{Error, InhibitEvent, Listener, MultiClick};
+use input::event::device::DeviceEvent;
+use input::event::keyboard::{KeyState, KeyboardEventTrait};
+use input::event::pointer::ButtonState;
+use input::event::pointer::PointerEvent::{Button, Motion, MotionAbsolute, ScrollWheel};
+use input::event::pointer::{Axis, PointerEventTrait, PointerScrollEvent, PointerScrollWheelEvent};
+use input::event::EventTrait;
+use input::{Device, DeviceCapability, Libinput, LibinputInterface};
+use nix::fcntl::{open, OFlag};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::stat::Mode;
+use nix::unistd::close;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Devices opened by libinput, shared with the listening loop so that these can be grabbed.
+type OpenDevices = Rc<RefCell<HashMap<PathBuf, RawFd>>>;
+
+pub(crate) fn process_message(listener: Listener, grab: bool, multi_click: MultiClick, seat: &str) {
+    struct LibinputInterfaceRaw {
+        devices: OpenDevices,
+    }
+
+    impl LibinputInterface for LibinputInterfaceRaw {
+        fn open_restricted(&mut self, path: &Path, flags: i32) -> std::result::Result<RawFd, i32> {
+            if let Ok(fd) = open(path, OFlag::from_bits_truncate(flags), Mode::empty()) {
+                self.devices.borrow_mut().insert(path.to_path_buf(), fd);
+                Ok(fd)
+            } else {
+                Err(1)
+            }
+        }
+
+        fn close_restricted(&mut self, fd: RawFd) {
+            // Closing releases the grab as well.
+            self.devices.borrow_mut().retain(|_, open| *open != fd);
+            let _ = close(fd);
+        }
+    }
+    let devices = OpenDevices::default();
+    let mut libinput = Libinput::new_with_udev(LibinputInterfaceRaw {
+        devices: devices.clone(),
+    });
+    if libinput.udev_assign_seat(seat).is_err() {
+        report(Err(Error::DeviceUnavailable(format!(
+            "libinput cannot assign {}",
+            seat
+        ))));
+        return;
+    }
+    let pollfd = PollFd::new(libinput.as_raw_fd(), PollFlags::POLLIN);
+    let mut session = Session {
+        observer: Observer::new(listener, grab, multi_click),
+        devices,
+        wheel: WheelAccumulator::default(),
+        motion: (0.0, 0.0),
+    };
+    // Timeout so that the shutdown of the context is noticed.
+    while session.observer.listener.is_listening() && poll(&mut [pollfd], 100).is_ok() {
+        if let Err(e) = libinput.dispatch() {
+            report(Err(Error::DeviceUnavailable(format!("libinput: {}", e))));
+            break;
+        }
+        #[allow(clippy::while_let_on_iterator)]
+        while let Some(event) = libinput.next() {
+            session.handle_libinput_event(event);
+        }
+    }
+}
+
+/// High resolution wheels report fractions of a click, those are summed up until a whole click.
+#[derive(Default)]
+struct WheelAccumulator {
+    horizontal: i32,
+    vertical: i32,
+}
+
+impl WheelAccumulator {
+    const CLICK: i32 = 120;
+
+    fn add(&mut self, horizontal_v120: f64, vertical_v120: f64) -> (i32, i32) {
+        self.horizontal += horizontal_v120 as i32;
+        self.vertical += vertical_v120 as i32;
+        let clicks = (self.horizontal / Self::CLICK, self.vertical / Self::CLICK);
+        self.horizontal %= Self::CLICK;
+        self.vertical %= Self::CLICK;
+        clicks
+    }
+}
+
+struct Session {
+    observer: Observer,
+    devices: OpenDevices,
+    wheel: WheelAccumulator,
+    /// Sub pixel motion not re-emitted yet.
+    motion: (f64, f64),
+}
+
+impl Session {
+    fn handle_libinput_event(&mut self, event: input::Event) {
+        if event.device().name() == DEVICE_NAME {
+            self.handle_injected(event);
+        } else {
+            self.handle_observed(event);
+        }
+    }
+
+    /// Events of our own uinput device, either injected by this library or re-emitted while grabbing.
+    fn handle_injected(&mut self, event: input::Event) {
+        let observer = &mut self.observer;
+        match event {
+            input::Event::Keyboard(kb) => {
+                let pressed = kb.key_state() == KeyState::Pressed;
+                if !observer.is_echo(kb.key(), pressed) {
+                    let key = kb_code_to_key(kb.key());
+                    if pressed {
+                        observer.listener.injected_key_down(key);
+                    } else {
+                        observer.listener.injected_key_up(key);
+                    }
+                }
+            }
+            input::Event::Pointer(Button(button_event)) => {
+                let code = button_event.button();
+                let pressed = button_event.button_state() == ButtonState::Pressed;
+                if let Some(button) =
+                    mouse_code_to_key(code).filter(|_| !observer.is_echo(code, pressed))
+                {
+                    if pressed {
+                        observer.button_down(button, button_event.time_usec());
+                    } else {
+                        observer.listener.button_up(button);
+                    }
+                }
+            }
+            input::Event::Pointer(Motion(motion_event)) => {
+                observer.moved(motion_event.dx(), motion_event.dy(), true);
+            }
+            // Re-emitted wheel cannot be told apart from the injected one, it was seen already.
+            input::Event::Pointer(ScrollWheel(scroll_event)) if !observer.grab => {
+                self.scrolled(&scroll_event, true);
+            }
+            other if !observer.grab => self.handle_observed(other),
+            _ => {}
+        }
+    }
+
+    /// Wheel rotation, `injected` if it was scrolled by this library.
+    fn scrolled(&mut self, scroll_event: &PointerScrollWheelEvent, injected: bool) {
+        let value = |axis| {
+            if scroll_event.has_axis(axis) {
+                scroll_event.scroll_value_v120(axis)
+            } else {
+                0.0
+            }
+        };
+        let (dx, dy) = self
+            .wheel
+            .add(value(Axis::Horizontal), value(Axis::Vertical));
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        // libinput considers down as positive.
+        let listener = &self.observer.listener;
+        if injected {
+            listener.injected_scrolled(dx, -dy);
+        } else {
+            let inhibit = listener.scrolled(dx, -dy);
+            if self.observer.grab && !inhibit.should_inhibit() {
+                mimpl::scroll(dx, -dy);
+            }
+        }
+    }
+
+    fn handle_observed(&mut self, event: input::Event) {
+        let listener = self.observer.listener.clone();
+        let grab = self.observer.grab;
+        match event {
+            input::Event::Device(DeviceEvent::Added(added)) => {
+                let device = added.device();
+                if grab && should_grab(&device) {
+                    self.set_grabbed(&device, true);
+                }
+            }
+            input::Event::Device(_) => {}
+            input::Event::Keyboard(kb) => {
+                let key = kb_code_to_key(kb.key());
+                match kb.key_state() {
+                    KeyState::Pressed => {
+                        let inhibit = listener.key_down(key);
+                        self.observer.forward_down(kb.key(), inhibit);
+                    }
+                    KeyState::Released => {
+                        listener.key_up(key);
+                        self.observer.forward_up(kb.key());
+                    }
+                }
+            }
+            input::Event::Pointer(Button(button_event)) => {
+                let code = button_event.button();
+                let mapped = mouse_code_to_key(code);
+                match button_event.button_state() {
+                    ButtonState::Pressed => {
+                        let inhibit = mapped
+                            .map(|button| {
+                                self.observer.button_down(button, button_event.time_usec())
+                            })
+                            .unwrap_or(InhibitEvent::No);
+                        self.observer.forward_down(code, inhibit);
+                    }
+                    ButtonState::Released => {
+                        if let Some(button) = mapped {
+                            listener.button_up(button);
+                        }
+                        self.observer.forward_up(code);
+                    }
+                }
+            }
+            input::Event::Pointer(Motion(motion_event)) => {
+                if grab {
+                    // Unaccelerated, the acceleration is applied again to the re-emitted motion.
+                    let (x, y) = (
+                        self.motion.0 + motion_event.dx_unaccelerated(),
+                        self.motion.1 + motion_event.dy_unaccelerated(),
+                    );
+                    let (dx, dy) = (x.trunc(), y.trunc());
+                    self.motion = (x - dx, y - dy);
+                    forward_motion(dx as i32, dy as i32);
+                }
+                self.observer
+                    .moved(motion_event.dx(), motion_event.dy(), false);
+            }
+            input::Event::Pointer(MotionAbsolute(motion_event)) => {
+                if grab {
+                    // Cannot be re-emitted through the relative uinput device, let it through.
+                    self.set_grabbed(&motion_event.device(), false);
+                }
+                self.observer.track_mouse();
+            }
+            input::Event::Pointer(ScrollWheel(scroll_event)) => {
+                self.scrolled(&scroll_event, false);
+            }
+            input::Event::Pointer(_) => {}
+            input::Event::Touch(_) => { /*println!("touch")*/ }
+            input::Event::Tablet(_) => { /*println!("touch2")*/ }
+            input::Event::TabletPad(_) => { /*println!("touch3")*/ }
+            input::Event::Gesture(_) => { /*println!("touch4")*/ }
+            input::Event::Switch(_) => { /*println!("touch5")*/ }
+            _other => { /*println!("A different event={}", _other)*/ }
+        }
+    }
+
+    fn set_grabbed(&self, device: &Device, grabbed: bool) {
+        let path = Path::new("/dev/input").join(device.sysname());
+        if let Some(fd) = self.devices.borrow().get(&path) {
+            if let Err(e) = unsafe { eviocgrab(*fd, grabbed as _) } {
+                eprintln!("mki: unable to grab {}: {}", device.name(), e);
+            }
+        }
+    }
+}
+
+/// Keyboards and mice, touchpads are pointers too but their gestures cannot be re-emitted.
+fn should_grab(device: &Device) -> bool {
+    (device.has_capability(DeviceCapability::Keyboard)
+        || device.has_capability(DeviceCapability::Pointer))
+        && !device.has_capability(DeviceCapability::Gesture)
+        && !device.has_capability(DeviceCapability::Touch)
+        && !device.has_capability(DeviceCapability::TabletTool)
+        && device.name() != DEVICE_NAME
+}
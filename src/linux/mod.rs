@@ -1,90 +1,294 @@
+mod evdev;
 pub mod keyboard_mouse;
+#[cfg(feature = "libinput")]
+mod libinput;
 
-use crate::details::registry;
-use crate::keyboard_mouse::{kb_code_to_key, mouse_code_to_key};
-use crate::Event;
-use input::event::keyboard::{KeyState, KeyboardEventTrait};
-use input::event::pointer::ButtonState;
-use input::event::pointer::PointerEvent::Button;
-use input::{Libinput, LibinputInterface};
-use nix::fcntl::{open, OFlag};
-use nix::poll::{poll, PollFd, PollFlags};
-use nix::sys::stat::Mode;
-use nix::unistd::close;
-use std::os::unix::io::AsRawFd;
-use std::os::unix::io::RawFd;
-use std::path::Path;
-
-pub(crate) fn install_hooks() {}
-
-pub(crate) fn process_message() {
-    struct LibinputInterfaceRaw;
-
-    impl LibinputInterface for LibinputInterfaceRaw {
-        fn open_restricted(&mut self, path: &Path, flags: i32) -> std::result::Result<RawFd, i32> {
-            if let Ok(fd) = open(path, OFlag::from_bits_truncate(flags), Mode::empty()) {
-                Ok(fd)
-            } else {
-                Err(1)
+pub use evdev::DeviceFilter;
+
+use crate::keyboard_mouse::{forward_key, kimpl, mimpl};
+use crate::mouse::ClickCounter;
+use crate::{Backend, Error, InhibitEvent, Keyboard, Listener, Mouse, MultiClick};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Backend injecting through uinput and xlib, listening through libinput or evdev, see `source`.
+/// By default events are only observed, `InhibitEvent` has no effect, see `grab`.
+#[derive(Default)]
+pub struct LinuxBackend {
+    grab: bool,
+    unicode: UnicodeInput,
+    multi_click: MultiClick,
+    source: InputSource,
+}
+
+/// Where `LinuxBackend` reads the events from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InputSource {
+    /// libinput through udev, the devices of the seat, usually `seat0`.
+    /// Requires the `libinput` feature, enabled by default.
+    #[cfg(feature = "libinput")]
+    Libinput(String),
+    /// `/dev/input/event*` read directly, neither udev nor libinput are needed.
+    /// Devices plugged in later are picked up through inotify.
+    Evdev(DeviceFilter),
+}
+
+impl Default for InputSource {
+    /// libinput on `seat0`, unless the `evdev` feature is enabled or the `libinput` one is not.
+    fn default() -> Self {
+        #[cfg(all(feature = "libinput", not(feature = "evdev")))]
+        return InputSource::Libinput("seat0".into());
+        #[cfg(any(not(feature = "libinput"), feature = "evdev"))]
+        return InputSource::Evdev(DeviceFilter::default());
+    }
+}
+
+/// How `Backend::type_char` types the characters.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum UnicodeInput {
+    /// Temporarily maps the keysym of the character to a spare X keycode and clicks it.
+    #[default]
+    Keysym,
+    /// Ctrl+Shift+U followed by the hex code point and Space, understood by GTK and IBus.
+    /// The digits are typed on the layout of the default context, see `set_layout`.
+    CtrlShiftU,
+}
+
+impl LinuxBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Backend reading the devices matching the filter directly, see `InputSource::Evdev`.
+    pub fn evdev(filter: DeviceFilter) -> Self {
+        Self::new().source(InputSource::Evdev(filter))
+    }
+
+    /// Where the events are read from, see `InputSource::default`.
+    pub fn source(mut self, source: InputSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Grab keyboards and mice exclusively, nothing else observes their events anymore.
+    /// The events are passed to the binds first and the ones not inhibited are re-emitted
+    /// through the `mki` uinput device, making `InhibitEvent` work as it does on Windows.
+    /// Touchpads, touchscreens and tablets are left alone,
+    /// as are devices that turn out to report absolute positions.
+    /// Wheel scrolled by this library is not passed to the binds while grabbing,
+    /// `bind_scroll` binds never get it.
+    pub fn grab(mut self, grab: bool) -> Self {
+        self.grab = grab;
+        self
+    }
+
+    /// When presses of the same button are reported as `Mouse::DoubleLeft`, `Mouse::TripleLeft` and the like,
+    /// these are reported right after the press of the button itself.
+    pub fn multi_click(mut self, multi_click: MultiClick) -> Self {
+        self.multi_click = multi_click;
+        self
+    }
+
+    /// How characters are typed, defaults to `UnicodeInput::Keysym`.
+    pub fn unicode(mut self, unicode: UnicodeInput) -> Self {
+        self.unicode = unicode;
+        self
+    }
+}
+
+impl Backend for LinuxBackend {
+    fn press(&self, key: Keyboard) {
+        kimpl::press(key)
+    }
+
+    fn release(&self, key: Keyboard) {
+        kimpl::release(key)
+    }
+
+    fn click(&self, key: Keyboard) {
+        kimpl::click(key)
+    }
+
+    fn try_press(&self, key: Keyboard) -> Result<(), Error> {
+        kimpl::try_press(key)
+    }
+
+    fn try_release(&self, key: Keyboard) -> Result<(), Error> {
+        kimpl::try_release(key)
+    }
+
+    fn try_click(&self, key: Keyboard) -> Result<(), Error> {
+        kimpl::try_click(key)
+    }
+
+    fn type_char(&self, c: char) {
+        kimpl::type_char(c, self.unicode)
+    }
+
+    fn is_toggled(&self, key: Keyboard) -> bool {
+        kimpl::is_toggled(key)
+    }
+
+    fn press_button(&self, button: Mouse) {
+        mimpl::press(button)
+    }
+
+    fn release_button(&self, button: Mouse) {
+        mimpl::release(button)
+    }
+
+    fn click_button(&self, button: Mouse) {
+        mimpl::click(button)
+    }
+
+    fn try_press_button(&self, button: Mouse) -> Result<(), Error> {
+        mimpl::try_press(button)
+    }
+
+    fn try_release_button(&self, button: Mouse) -> Result<(), Error> {
+        mimpl::try_release(button)
+    }
+
+    fn try_click_button(&self, button: Mouse) -> Result<(), Error> {
+        mimpl::try_click(button)
+    }
+
+    fn try_move_to(&self, x: i32, y: i32) -> Result<(), Error> {
+        mimpl::try_move_to(x, y)
+    }
+
+    fn move_to(&self, x: i32, y: i32) {
+        mimpl::move_to(x, y)
+    }
+
+    fn move_by(&self, x: i32, y: i32) {
+        mimpl::move_by(x, y)
+    }
+
+    fn try_move_by(&self, x: i32, y: i32) -> Result<(), Error> {
+        mimpl::try_move_by(x, y)
+    }
+
+    fn scroll(&self, dx: i32, dy: i32) {
+        mimpl::scroll(dx, dy)
+    }
+
+    fn position(&self) -> (i32, i32) {
+        mimpl::position()
+    }
+
+    fn try_position(&self) -> Result<(i32, i32), Error> {
+        mimpl::try_position()
+    }
+
+    fn click_at(&self, x: i32, y: i32, button: Mouse) {
+        mimpl::click_at(x, y, button)
+    }
+
+    fn listen(&self, listener: Listener) {
+        match &self.source {
+            #[cfg(feature = "libinput")]
+            InputSource::Libinput(seat) => {
+                libinput::process_message(listener, self.grab, self.multi_click, seat)
+            }
+            InputSource::Evdev(filter) => {
+                evdev::process_events(listener, self.grab, self.multi_click, filter)
             }
         }
+    }
+}
+
+// EVIOCGRAB from linux/input.h
+nix::ioctl_write_int!(eviocgrab, b'E', 0x90);
 
-        fn close_restricted(&mut self, fd: RawFd) {
-            let _ = close(fd);
+/// State of a listening loop shared by the event sources.
+struct Observer {
+    listener: Listener,
+    grab: bool,
+    clicks: ClickCounter,
+    /// Keys and buttons whose press was inhibited, their release is inhibited as well.
+    inhibited: HashSet<u32>,
+    /// Keys and buttons re-emitted but not observed back on our own device yet.
+    echoes: HashMap<(u32, bool), usize>,
+    /// Relative motion summed up since listening started, the position reported without an X display.
+    position: (f64, f64),
+}
+
+impl Observer {
+    fn new(listener: Listener, grab: bool, multi_click: MultiClick) -> Self {
+        Observer {
+            listener,
+            grab,
+            clicks: ClickCounter::new(multi_click),
+            inhibited: HashSet::new(),
+            echoes: HashMap::new(),
+            position: (0.0, 0.0),
         }
     }
-    let mut libinput = Libinput::new_with_udev(LibinputInterfaceRaw);
-    libinput.udev_assign_seat("seat0").unwrap();
-    let pollfd = PollFd::new(libinput.as_raw_fd(), PollFlags::POLLIN);
-    while poll(&mut [pollfd], -1).is_ok() {
-        libinput.dispatch().unwrap();
-        #[allow(clippy::while_let_on_iterator)]
-        while let Some(event) = libinput.next() {
-            handle_libinput_event(event);
+
+    /// Reports the press followed by the double or triple click it makes, if any.
+    fn button_down(&mut self, button: Mouse, time_usec: u64) -> InhibitEvent {
+        self.clicks
+            .button_down(&self.listener, button, Duration::from_micros(time_usec))
+    }
+
+    /// Relative motion of a device, `injected` if it is our own uinput device.
+    fn moved(&mut self, dx: f64, dy: f64, injected: bool) {
+        self.clicks.moved(dx, dy);
+        // While grabbing the motion is observed and then seen again once re-emitted, counted once.
+        if injected == self.grab {
+            self.position = (
+                (self.position.0 + dx).max(0.0),
+                (self.position.1 + dy).max(0.0),
+            );
         }
+        self.track_mouse();
     }
-}
 
-fn handle_libinput_event(event: input::Event) {
-    match event {
-        input::Event::Device(_) => {}
-        input::Event::Keyboard(kb) => {
-            let key = kb_code_to_key(kb.key());
-            match kb.key_state() {
-                KeyState::Pressed => {
-                    registry().event_down(Event::Keyboard(key));
-                }
-                KeyState::Released => {
-                    registry().event_up(Event::Keyboard(key));
-                }
-            }
+    // The devices only report the relative motion, the actual position is owned by the X server.
+    // Without one, e.g. on a kiosk, the summed up motion is reported instead.
+    fn track_mouse(&self) {
+        if self.listener.is_tracking_mouse() {
+            let (x, y) =
+                mimpl::try_position().unwrap_or((self.position.0 as i32, self.position.1 as i32));
+            self.listener.mouse_moved(x, y);
         }
-        input::Event::Pointer(Button(button_event)) => {
-            if let Some(mapped) = mouse_code_to_key(button_event.button()) {
-                match button_event.button_state() {
-                    ButtonState::Pressed => {
-                        registry().event_down(Event::Mouse(mapped));
-                    }
-                    ButtonState::Released => {
-                        registry().event_up(Event::Mouse(mapped));
-                    }
-                }
-            }
+    }
+
+    fn forward_down(&mut self, code: u32, inhibit: InhibitEvent) {
+        if !self.grab {
+            return;
+        }
+        if inhibit.should_inhibit() {
+            self.inhibited.insert(code);
+        } else {
+            self.forward_key(code, true);
+        }
+    }
+
+    fn forward_up(&mut self, code: u32) {
+        if self.grab && !self.inhibited.remove(&code) {
+            self.forward_key(code, false);
         }
-        input::Event::Pointer(_) => {
-            // This one gets invoked but it seems pretty useless
-            // if let Motion(motion_event) = &pointer {
-            //     println!("1 {} {}", motion_event.dx(), motion_event.dy());
-            // }
-            // if let MotionAbsolute(motion_event) = &pointer {
-            // Unfortunatelly this one does not get invoked?
-            // }
+    }
+
+    fn forward_key(&mut self, code: u32, pressed: bool) {
+        *self.echoes.entry((code, pressed)).or_default() += 1;
+        forward_key(code, pressed);
+    }
+
+    /// Whether this is a key or button re-emitted by `forward_key`.
+    fn is_echo(&mut self, code: u32, pressed: bool) -> bool {
+        match self.echoes.get_mut(&(code, pressed)) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                true
+            }
+            Some(_) => {
+                self.echoes.remove(&(code, pressed));
+                true
+            }
+            None => false,
         }
-        input::Event::Touch(_) => { /*println!("touch")*/ }
-        input::Event::Tablet(_) => { /*println!("touch2")*/ }
-        input::Event::TabletPad(_) => { /*println!("touch3")*/ }
-        input::Event::Gesture(_) => { /*println!("touch4")*/ }
-        input::Event::Switch(_) => { /*println!("touch5")*/ }
-        _other => { /*println!("A different event={}", _other)*/ }
     }
 }
@@ -1,32 +1,157 @@
+use crate::{InhibitEvent, Listener};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 
-#[cfg(target_os = "windows")] // Not sure how to detect double on linux
+/// Buttons of both platforms, injecting one the platform does not have does nothing,
+/// `Mouse::try_press` and the like report those as `Error::Unsupported`.
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Mouse {
     Left,
     DoubleLeft,
+    TripleLeft,
     Right,
     DoubleRight,
+    TripleRight,
     Middle,
     DoubleMiddle,
+    TripleMiddle,
     Side, // XBUTTON1
     DoubleSide,
+    TripleSide,
     Extra, // XBUTTON2
     DoubleExtra,
-}
-
-#[cfg(target_os = "linux")]
-#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
-pub enum Mouse {
-    Left,
-    Right,
-    Middle,
-    Side,
-    Extra,
+    TripleExtra,
+    // Linux only, `Unsupported` on Windows.
     Forward,
     Back,
     Task,
+    // Wheel, clicked once per wheel click. Press scrolls, release does nothing.
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+
+/// When presses of the same button count as a double or triple click,
+/// used where the platform does not report those, see `LinuxBackend::multi_click`.
+/// Windows uses its own double click settings.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MultiClick {
+    /// Longest time between two presses.
+    pub interval: Duration,
+    /// Furthest the pointer can move between two presses, in pixels.
+    pub tolerance: u32,
+}
+
+impl Default for MultiClick {
+    fn default() -> Self {
+        MultiClick {
+            interval: Duration::from_millis(400),
+            tolerance: 4,
+        }
+    }
+}
+
+/// Counts the consecutive presses of a button, the fourth one starts over.
+#[derive(Default)]
+pub(crate) struct ClickCounter {
+    settings: MultiClick,
+    /// Button, timestamp and count of the last press.
+    last: Option<(Mouse, Duration, u32)>,
+    /// Pointer motion since the last press.
+    moved: (f64, f64),
+}
+
+impl ClickCounter {
+    pub(crate) fn new(settings: MultiClick) -> Self {
+        ClickCounter {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn moved(&mut self, dx: f64, dy: f64) {
+        self.moved = (self.moved.0 + dx, self.moved.1 + dy);
+    }
+
+    /// Double or triple click variant to report along with the press at given timestamp, if any.
+    pub(crate) fn pressed(&mut self, button: Mouse, at: Duration) -> Option<Mouse> {
+        let (dx, dy) = std::mem::take(&mut self.moved);
+        let close = (dx * dx + dy * dy).sqrt() <= self.settings.tolerance as f64;
+        let count = match self.last {
+            Some((last, last_at, count))
+                if last == button
+                    && close
+                    && count < 3
+                    && at.saturating_sub(last_at) <= self.settings.interval =>
+            {
+                count + 1
+            }
+            _ => 1,
+        };
+        self.last = Some((button, at, count));
+        button.clicked(count)
+    }
+
+    /// Reports the press followed by the double or triple click it makes, if any.
+    pub(crate) fn button_down(
+        &mut self,
+        listener: &Listener,
+        button: Mouse,
+        at: Duration,
+    ) -> InhibitEvent {
+        let inhibit = listener.button_down(button);
+        match self.pressed(button, at) {
+            Some(clicked) if listener.button_click(clicked).should_inhibit() => InhibitEvent::Yes,
+            _ => inhibit,
+        }
+    }
+}
+
+impl Mouse {
+    /// Variant reported when the button is clicked `count` times in a row,
+    /// `None` unless the count is 2 or 3 and the button has such variants.
+    pub fn clicked(self, count: u32) -> Option<Mouse> {
+        use Mouse::*;
+        Some(match (self, count) {
+            (Left, 2) => DoubleLeft,
+            (Left, 3) => TripleLeft,
+            (Right, 2) => DoubleRight,
+            (Right, 3) => TripleRight,
+            (Middle, 2) => DoubleMiddle,
+            (Middle, 3) => TripleMiddle,
+            (Side, 2) => DoubleSide,
+            (Side, 3) => TripleSide,
+            (Extra, 2) => DoubleExtra,
+            (Extra, 3) => TripleExtra,
+            _ => return None,
+        })
+    }
+
+    /// Button pressed to make this click, the button itself unless it is a double or triple click.
+    pub fn single(self) -> Mouse {
+        use Mouse::*;
+        match self {
+            DoubleLeft | TripleLeft => Left,
+            DoubleRight | TripleRight => Right,
+            DoubleMiddle | TripleMiddle => Middle,
+            DoubleSide | TripleSide => Side,
+            DoubleExtra | TripleExtra => Extra,
+            other => other,
+        }
+    }
+
+    /// Wheel clicks represented by this button, as in `Mouse::scroll`.
+    pub(crate) fn scroll_delta(&self) -> Option<(i32, i32)> {
+        match self {
+            Mouse::ScrollUp => Some((0, 1)),
+            Mouse::ScrollDown => Some((0, -1)),
+            Mouse::ScrollLeft => Some((-1, 0)),
+            Mouse::ScrollRight => Some((1, 0)),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Mouse {
@@ -34,3 +159,30 @@ impl fmt::Display for Mouse {
         f.write_fmt(format_args!("{:?}", self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mouse::{ClickCounter, MultiClick};
+    use crate::Mouse::*;
+    use std::time::Duration;
+
+    #[test]
+    fn multi_click() {
+        let mut counter = ClickCounter::new(MultiClick::default());
+        let at = Duration::from_millis;
+        assert_eq!(None, counter.pressed(Left, at(0)));
+        assert_eq!(Some(DoubleLeft), counter.pressed(Left, at(300)));
+        counter.moved(2.0, -2.0);
+        assert_eq!(Some(TripleLeft), counter.pressed(Left, at(600)));
+        assert_eq!(None, counter.pressed(Left, at(700)));
+
+        // Too slow, too far or another button starts over.
+        assert_eq!(None, counter.pressed(Left, at(1200)));
+        counter.moved(10.0, 0.0);
+        assert_eq!(None, counter.pressed(Left, at(1300)));
+        assert_eq!(None, counter.pressed(Right, at(1400)));
+        assert_eq!(Some(DoubleRight), counter.pressed(Right, at(1500)));
+        assert_eq!(None, counter.pressed(Forward, at(1600)));
+        assert_eq!(None, counter.pressed(Forward, at(1700)));
+    }
+}
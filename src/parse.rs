@@ -1,14 +1,60 @@
 use crate::{
-    are_pressed, get_state, print_pressed_state, register_hotkey, set_state, Keyboard, Mouse,
+    are_pressed, bind_button_in, bind_scroll, get_state, layer_key, print_pressed_state,
+    register_chords, register_hotkey_in, register_leader, remap_combo, set_state, Chords, Hotkey,
+    InhibitEvent, Keyboard, LayerMode, Leader, Matching, Modifier, Mouse, BASE_LAYER,
 };
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::thread;
 use std::time::Duration;
 
 #[derive(Deserialize, Serialize)]
 struct Config {
+    #[serde(default)]
     bind: Vec<Bind>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    remap: Vec<Remap>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    leader: Vec<LeaderBind>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<Layer>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Layer {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    activate: Option<Activate>,
+    /// Same as the top level binds, but only active while the layer is.
+    #[serde(default)]
+    bind: Vec<Bind>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Activate {
+    key: Keyboard,
+    mode: LayerMode,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Remap {
+    from: Vec<Keyboard>,
+    to: Vec<Keyboard>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct LeaderBind {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    leader: Keyboard,
+    keys: Vec<Keyboard>,
+    /// Milliseconds between the keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+    #[serde(default)]
+    suppress: bool,
+    action: Action,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -16,20 +62,80 @@ struct Bind {
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(flatten)]
-    input: Input,
+    input: Input<BindKey>,
+    #[serde(default, skip_serializing_if = "is_exact")]
+    matching: Matching,
+    /// Milliseconds between the strokes of chords.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+    /// Binds the wheel instead of a key or button, see `ForwardScroll` for using the clicks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scroll: Option<ScrollInput>,
 
     action: Action,
 }
 
 #[derive(Deserialize, Serialize)]
-struct Input {
+struct ScrollInput {
+    #[serde(default)]
+    inhibit: bool,
+}
+
+fn is_exact(matching: &Matching) -> bool {
+    *matching == Matching::Exact
+}
+
+/// Keys of a bind may contain modifiers matching either side such as `Ctrl`, see `Modifier`.
+/// Once any of them is a list all of them are strokes of chords, e.g. `[[Ctrl, K], [Ctrl, C]]`.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum BindKey {
+    Key(Modifier),
+    Chord(Vec<Modifier>),
+}
+
+impl From<Keyboard> for BindKey {
+    fn from(key: Keyboard) -> Self {
+        BindKey::Key(key.into())
+    }
+}
+
+impl Bind {
+    fn chords(keys: &[BindKey]) -> Result<Option<Chords>, String> {
+        if keys.iter().all(|key| matches!(key, BindKey::Key(_))) {
+            return Ok(None);
+        }
+        let strokes = keys
+            .iter()
+            .map(|key| match key {
+                BindKey::Key(key) => Hotkey::try_from(&[*key][..]),
+                BindKey::Chord(keys) => Hotkey::try_from(keys.as_slice()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Chords::new(strokes)))
+    }
+
+    fn hotkey(keys: &[BindKey]) -> Result<Hotkey, String> {
+        let keys: Vec<Modifier> = keys
+            .iter()
+            .filter_map(|key| match key {
+                BindKey::Key(key) => Some(*key),
+                BindKey::Chord(_) => None,
+            })
+            .collect();
+        Hotkey::try_from(keys.as_slice())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Input<K = Keyboard> {
     #[serde(skip_serializing_if = "Option::is_none")]
-    key: Option<Vec<Keyboard>>,
+    key: Option<Vec<K>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     button: Option<Vec<Mouse>>,
 }
 
-impl Input {
+impl<K> Input<K> {
     fn validate(&self) -> Result<(), serde_yaml::Error> {
         if self.key.is_none() && self.button.is_none() {
             Err(serde_yaml::Error::custom("Bind had neither key nor button"))
@@ -56,10 +162,10 @@ impl Input {
         }
     }
     #[allow(unused)]
-    fn key(key: Keyboard) -> Self {
+    fn key(key: impl Into<K>) -> Self {
         Input {
             button: None,
-            key: Some(vec![key]),
+            key: Some(vec![key.into()]),
         }
     }
 }
@@ -83,6 +189,22 @@ struct StateMatches {
     action: Vec<Action>,
 }
 
+#[derive(Deserialize, Serialize)]
+struct Scroll {
+    #[serde(default)]
+    x: i32,
+    #[serde(default)]
+    y: i32,
+}
+
+/// Scrolls by the clicks the scroll bind was invoked with.
+#[derive(Deserialize, Serialize)]
+struct ForwardScroll {
+    /// Vertical clicks become horizontal ones and the other way around.
+    #[serde(default)]
+    swap: bool,
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 enum Action {
@@ -93,41 +215,52 @@ enum Action {
     Press(Input),
     Release(Input),
     Click(Input),
-    Sleep(u64), // Milliseconds
+    Scroll(Scroll), // Wheel clicks, positive y is up, positive x is right
+    ForwardScroll(ForwardScroll), // Only in scroll binds
+    Sleep(u64),     // Milliseconds
     SetState(SetState),
     Println(String),
     PrintState(String),
     PrintPressedState,
 }
 
-fn validate_actions(actions: &[Action]) -> serde_yaml::Result<()> {
+fn validate_actions(actions: &[Action], scroll: bool) -> serde_yaml::Result<()> {
     for a in actions {
-        validate_action(a)?;
+        validate_action(a, scroll)?;
     }
     Ok(())
 }
 
-fn validate_action(action: &Action) -> serde_yaml::Result<()> {
+/// `scroll` is whether the actions belong to a scroll bind.
+fn validate_action(action: &Action, scroll: bool) -> serde_yaml::Result<()> {
     match action {
         Action::Multi(actions) => {
-            validate_actions(actions)?;
+            validate_actions(actions, scroll)?;
         }
         Action::Pressed(pressed) => {
             pressed.input.validate()?;
             if pressed.input.key.is_none() {
                 return Err(serde_yaml::Error::custom("Pressed can only check keys."));
             }
-            validate_actions(&pressed.action)?;
+            validate_actions(&pressed.action, scroll)?;
         }
         Action::StateMatches(state_matches) => {
-            validate_actions(&state_matches.action)?;
+            validate_actions(&state_matches.action, scroll)?;
         }
         Action::WhileStateMatches(state_matches) => {
-            validate_actions(&state_matches.action)?;
+            validate_actions(&state_matches.action, scroll)?;
+        }
+        Action::ForwardScroll(_) => {
+            if !scroll {
+                return Err(serde_yaml::Error::custom(
+                    "Forward scroll is only supported in scroll binds",
+                ));
+            }
         }
         Action::Press(_)
         | Action::Release(_)
         | Action::Click(_)
+        | Action::Scroll(_)
         | Action::Sleep(_)
         | Action::SetState(_)
         | Action::Println(_)
@@ -138,34 +271,35 @@ fn validate_action(action: &Action) -> serde_yaml::Result<()> {
     Ok(())
 }
 
-fn handle_actions(actions: &[Action]) {
+fn handle_actions(actions: &[Action], scrolled: (i32, i32)) {
     for a in actions {
-        handle_action(a);
+        handle_action(a, scrolled);
     }
 }
 
-fn handle_action(action: &Action) {
+/// `scrolled` are the wheel clicks of a scroll bind, zero for the other binds.
+fn handle_action(action: &Action, scrolled: (i32, i32)) {
     match action {
         Action::Multi(actions) => {
-            handle_actions(actions);
+            handle_actions(actions, scrolled);
         }
         Action::Pressed(pressed) => {
             let keys = pressed.input.key.as_ref().unwrap();
             if are_pressed(keys) {
-                handle_actions(&pressed.action);
+                handle_actions(&pressed.action, scrolled);
             }
         }
         Action::StateMatches(state_matches) => {
             if let Some(state) = get_state(&state_matches.name) {
                 if state == state_matches.value {
-                    handle_actions(&state_matches.action);
+                    handle_actions(&state_matches.action, scrolled);
                 }
             }
         }
         Action::WhileStateMatches(state_matches) => {
             while let Some(state) = get_state(&state_matches.name) {
                 if state == state_matches.value {
-                    handle_actions(&state_matches.action);
+                    handle_actions(&state_matches.action, scrolled);
                 } else {
                     break;
                 }
@@ -179,7 +313,9 @@ fn handle_action(action: &Action) {
             }
             if let Some(buttons) = &input.button {
                 for b in buttons {
-                    b.press();
+                    if let Err(e) = b.try_press() {
+                        println!("{}", e);
+                    }
                 }
             }
         }
@@ -191,7 +327,9 @@ fn handle_action(action: &Action) {
             }
             if let Some(buttons) = &input.button {
                 for b in buttons {
-                    b.release();
+                    if let Err(e) = b.try_release() {
+                        println!("{}", e);
+                    }
                 }
             }
         }
@@ -203,10 +341,23 @@ fn handle_action(action: &Action) {
             }
             if let Some(buttons) = &input.button {
                 for b in buttons {
-                    b.click()
+                    if let Err(e) = b.try_click() {
+                        println!("{}", e);
+                    }
                 }
             }
         }
+        Action::Scroll(scroll) => {
+            Mouse::scroll(scroll.x, scroll.y);
+        }
+        Action::ForwardScroll(forward) => {
+            let (dx, dy) = scrolled;
+            if forward.swap {
+                Mouse::scroll(dy, dx);
+            } else {
+                Mouse::scroll(dx, dy);
+            }
+        }
         Action::Sleep(millis) => {
             thread::sleep(Duration::from_millis(*millis));
         }
@@ -225,44 +376,160 @@ fn handle_action(action: &Action) {
 
 pub fn load_config(content: &str) -> Result<(), serde_yaml::Error> {
     let config: Config = serde_yaml::from_str(content)?;
-    for bind in config.bind {
-        bind.input.validate()?;
+    if config.remap.iter().any(|remap| remap.from.is_empty()) {
+        return Err(serde_yaml::Error::custom("Remap had empty from"));
+    }
+    if config.leader.iter().any(|leader| leader.keys.is_empty()) {
+        return Err(serde_yaml::Error::custom("Leader had empty keys"));
+    }
+    for leader in &config.leader {
+        validate_action(&leader.action, false)?;
+    }
+    for remap in config.remap {
+        println!("Now remapping: {:?} to: {:?}", remap.from, remap.to);
+        remap_combo(&remap.from, &remap.to).forget();
+    }
+    for bind in config.leader {
+        let mut leader = Leader::new(bind.leader, &bind.keys).suppress(bind.suppress);
+        if let Some(millis) = bind.timeout {
+            leader = leader.timeout(Duration::from_millis(millis));
+        }
+        println!(
+            "Now binding leader: {:?} followed by: {:?}",
+            bind.leader, bind.keys
+        );
+        if let Some(description) = bind.description {
+            println!("description: {}", description);
+        }
         let action = bind.action;
-        validate_action(&action)?;
-        match (bind.input.key, bind.input.button) {
-            (Some(keys), None) => {
-                println!("Now binding a hotkey for: {:?}", keys);
+        register_leader(leader, move || {
+            handle_action(&action, (0, 0));
+        })
+        .forget();
+    }
+    for bind in config.bind {
+        register_bind(bind, BASE_LAYER)?;
+    }
+    for layer in config.layers {
+        if let Some(activate) = layer.activate {
+            println!(
+                "Now binding layer: {} to: {:?} ({:?})",
+                layer.name, activate.key, activate.mode
+            );
+            layer_key(activate.key, &layer.name, activate.mode).forget();
+        }
+        for bind in layer.bind {
+            register_bind(bind, &layer.name)?;
+        }
+    }
+    Ok(())
+}
+
+fn register_bind(bind: Bind, layer: &str) -> Result<(), serde_yaml::Error> {
+    if let Some(scroll) = bind.scroll {
+        return register_scroll(scroll, bind.input, bind.action, layer);
+    }
+    bind.input.validate()?;
+    let action = bind.action;
+    validate_action(&action, false)?;
+    match (bind.input.key, bind.input.button) {
+        (Some(keys), None) => {
+            if let Some(chords) = Bind::chords(&keys).map_err(serde_yaml::Error::custom)? {
+                if layer != BASE_LAYER {
+                    return Err(serde_yaml::Error::custom(
+                        "Chords cannot be bound in a layer",
+                    ));
+                }
+                let chords = match bind.timeout {
+                    Some(millis) => chords.timeout(Duration::from_millis(millis)),
+                    None => chords,
+                };
+                println!("Now binding chords for: {:?}", keys);
                 if let Some(description) = bind.description {
                     println!("description: {}", description);
                 }
-                register_hotkey(&keys, move || {
-                    handle_action(&action);
+                register_chords(chords, move || {
+                    handle_action(&action, (0, 0));
                 })
+                .forget();
+                return Ok(());
             }
-            (None, Some(buttons)) => {
-                if buttons.len() != 1 {
-                    panic!(
-                        "Mouse combination cannot be bound, single mouse expected: {:?}",
-                        buttons
-                    );
-                }
-                let button = buttons[0];
-                button.bind(move |_mouse_why_is_this_here| {
-                    handle_action(&action);
-                })
+            let hotkey = Bind::hotkey(&keys)
+                .map_err(serde_yaml::Error::custom)?
+                .matching(bind.matching);
+            println!("Now binding a hotkey for: {:?} in layer: {}", keys, layer);
+            if let Some(description) = bind.description {
+                println!("description: {}", description);
             }
-            _ => {
-                unreachable!("Checked in validate_action");
+            register_hotkey_in(layer, hotkey, move || {
+                handle_action(&action, (0, 0));
+            })
+            .forget();
+        }
+        (None, Some(buttons)) => {
+            if buttons.len() != 1 {
+                panic!(
+                    "Mouse combination cannot be bound, single mouse expected: {:?}",
+                    buttons
+                );
             }
+            bind_button_in(
+                layer,
+                buttons[0],
+                crate::Action::handle_mouse(move |_mouse_why_is_this_here| {
+                    handle_action(&action, (0, 0));
+                }),
+            )
+            .forget();
         }
+        _ => {
+            unreachable!("Checked in validate_action");
+        }
+    }
+    Ok(())
+}
+
+fn register_scroll(
+    scroll: ScrollInput,
+    input: Input<BindKey>,
+    action: Action,
+    layer: &str,
+) -> Result<(), serde_yaml::Error> {
+    if input.key.is_some() || input.button.is_some() {
+        return Err(serde_yaml::Error::custom(
+            "Bind had scroll together with key or button",
+        ));
+    }
+    if layer != BASE_LAYER {
+        return Err(serde_yaml::Error::custom(
+            "Scroll cannot be bound in a layer",
+        ));
     }
+    validate_action(&action, true)?;
+    let inhibit = if scroll.inhibit {
+        InhibitEvent::Yes
+    } else {
+        InhibitEvent::No
+    };
+    println!("Now binding the wheel");
+    bind_scroll(inhibit, move |dx, dy| {
+        handle_action(&action, (dx, dy));
+    })
+    .forget();
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parse::{Action, Bind, Config, Input, SetState, StateMatches};
-    use crate::Keyboard::{LeftControl, Number0, Number1, D, E, H, K, L, R, S, W};
+    use crate::parse::{
+        Action, Activate, Bind, BindKey, Config, Input, Layer, LeaderBind, Pressed, Remap, Scroll,
+        ScrollInput, SetState, StateMatches,
+    };
+    use crate::Keyboard::{
+        BackSpace, CapsLock, Escape, LeftControl, LeftShift, Number0, Number1, B, C, D, E, G, H, K,
+        L, R, S, W,
+    };
+    use crate::{Hotkey, LayerMode, Matching, Modifier, Mouse};
 
     #[test]
     fn example() {
@@ -274,9 +541,12 @@ mod tests {
                             .into(),
                     ),
                     input: Input {
-                        key: Some(vec![LeftControl, H]),
+                        key: Some(vec![LeftControl.into(), H.into()]),
                         button: None,
                     },
+                    matching: Matching::Exact,
+                    timeout: None,
+                    scroll: None,
                     action: Action::Multi(vec![
                         Action::WhileStateMatches(StateMatches {
                             name: "test".into(),
@@ -289,6 +559,9 @@ mod tests {
                 Bind {
                     description: Some("S: Set state to 1 then print it".into()),
                     input: Input::key(S),
+                    matching: Matching::Exact,
+                    timeout: None,
+                    scroll: None,
                     action: Action::Multi(vec![
                         Action::SetState(SetState {
                             name: "test".into(),
@@ -300,6 +573,9 @@ mod tests {
                 Bind {
                     description: Some("R: Set state to 0 then print it".into()),
                     input: Input::key(R),
+                    matching: Matching::Exact,
+                    timeout: None,
+                    scroll: None,
                     action: Action::Multi(vec![
                         Action::SetState(SetState {
                             name: "test".into(),
@@ -311,6 +587,9 @@ mod tests {
                 Bind {
                     description: Some("If state 1 then click 1; If state 0 then click 0".into()),
                     input: Input::key(D),
+                    matching: Matching::Exact,
+                    timeout: None,
+                    scroll: None,
                     action: Action::Multi(vec![
                         Action::StateMatches(StateMatches {
                             name: "test".into(),
@@ -325,6 +604,9 @@ mod tests {
                     ]),
                 },
             ],
+            remap: vec![],
+            leader: vec![],
+            layers: vec![],
         };
         assert_eq!(
             r#"---
@@ -394,11 +676,17 @@ bind:
             bind: vec![Bind {
                 description: Some("Whenever Ctrl+L is clicked click K as well".into()),
                 input: Input {
-                    key: Some(vec![LeftControl, L]),
+                    key: Some(vec![LeftControl.into(), L.into()]),
                     button: None,
                 },
+                matching: Matching::Exact,
+                timeout: None,
+                scroll: None,
                 action: Action::Click(Input::key(K)),
             }],
+            remap: vec![],
+            leader: vec![],
+            layers: vec![],
         };
         assert_eq!(
             r#"---
@@ -415,4 +703,221 @@ bind:
             serde_yaml::to_string(&c).unwrap()
         );
     }
+
+    #[test]
+    fn scroll() {
+        let c = Config {
+            bind: vec![Bind {
+                description: Some("Ctrl + Wheel up scrolls right instead".into()),
+                input: Input {
+                    key: None,
+                    button: Some(vec![Mouse::ScrollUp]),
+                },
+                matching: Matching::Exact,
+                timeout: None,
+                scroll: None,
+                action: Action::Pressed(Pressed {
+                    input: Input::key(LeftControl),
+                    action: vec![Action::Scroll(Scroll { x: 1, y: 0 })],
+                }),
+            }],
+            remap: vec![],
+            leader: vec![],
+            layers: vec![],
+        };
+        let yaml = r#"---
+bind:
+  - description: Ctrl + Wheel up scrolls right instead
+    button:
+      - ScrollUp
+    action:
+      pressed:
+        input:
+          key:
+            - LeftControl
+        action:
+          - scroll:
+              x: 1
+              y: 0
+"#;
+        assert_eq!(yaml, serde_yaml::to_string(&c).unwrap());
+        let parsed: Action = serde_yaml::from_str("scroll:\n  y: -2").unwrap();
+        assert!(matches!(parsed, Action::Scroll(Scroll { x: 0, y: -2 })));
+
+        let c: Config = serde_yaml::from_str(
+            r#"---
+bind:
+  - description: Shift + Wheel scrolls sideways
+    scroll:
+      inhibit: true
+    action:
+      pressed:
+        input:
+          key: [LeftShift]
+        action:
+          - forward-scroll:
+              swap: true
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            c.bind.as_slice(),
+            [Bind {
+                scroll: Some(ScrollInput { inhibit: true }),
+                input: Input {
+                    key: None,
+                    button: None
+                },
+                ..
+            }]
+        ));
+        assert!(
+            crate::load_config("bind:\n  - key: [B]\n    action:\n      forward-scroll: {}")
+                .is_err()
+        );
+        assert!(crate::load_config(
+            "bind:\n  - scroll: {}\n    key: [B]\n    action: print-pressed-state"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn remap() {
+        let c: Config = serde_yaml::from_str(
+            r#"---
+remap:
+  - from: [CapsLock]
+    to: [Escape]
+  - from: [LeftControl, H]
+    to: [BackSpace]
+"#,
+        )
+        .unwrap();
+        assert!(c.bind.is_empty());
+        assert!(matches!(
+            c.remap.as_slice(),
+            [
+                Remap { from: a, to: b },
+                Remap { from: c, to: d },
+            ] if a == &[CapsLock] && b == &[Escape] && c == &[LeftControl, H] && d == &[BackSpace]
+        ));
+    }
+
+    #[test]
+    fn hotkey() {
+        let c: Config = serde_yaml::from_str(
+            r#"---
+bind:
+  - key: [Ctrl, LeftShift, B]
+    matching: order-insensitive
+    action:
+      click:
+        key: [K]
+"#,
+        )
+        .unwrap();
+        let bind = &c.bind[0];
+        assert_eq!(
+            Some(vec![
+                BindKey::Key(Modifier::Ctrl),
+                BindKey::Key(Modifier::Key(LeftShift)),
+                BindKey::Key(Modifier::Key(B))
+            ]),
+            bind.input.key
+        );
+        assert_eq!(Matching::OrderInsensitive, bind.matching);
+        assert!(serde_yaml::from_str::<Config>(
+            "bind:\n  - key: [Ctrl]\n    action: print-pressed-state"
+        )
+        .is_ok());
+        assert!(
+            crate::load_config("bind:\n  - key: [B, Ctrl]\n    action: print-pressed-state")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn chords() {
+        let c: Config = serde_yaml::from_str(
+            r#"---
+bind:
+  - key: [[Ctrl, K], [Ctrl, C]]
+    timeout: 500
+    action: print-pressed-state
+  - key: [[Ctrl, K], W]
+    action: print-pressed-state
+"#,
+        )
+        .unwrap();
+        let chords = Bind::chords(c.bind[0].input.key.as_ref().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            &[
+                Hotkey::new(K).modifier(Modifier::Ctrl),
+                Hotkey::new(C).modifier(Modifier::Ctrl)
+            ],
+            chords.strokes()
+        );
+        assert_eq!(Some(500), c.bind[0].timeout);
+        let chords = Bind::chords(c.bind[1].input.key.as_ref().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(Hotkey::new(W), chords.strokes()[1]);
+    }
+
+    #[test]
+    fn leader() {
+        let c: Config = serde_yaml::from_str(
+            r#"---
+leader:
+  - description: git status
+    leader: CapsLock
+    keys: [G, S]
+    suppress: true
+    action:
+      println: git status
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            c.leader.as_slice(),
+            [LeaderBind { leader: CapsLock, keys, timeout: None, suppress: true, .. }] if keys == &[G, S]
+        ));
+        assert!(crate::load_config(
+            "leader:\n  - leader: CapsLock\n    keys: []\n    action: print-pressed-state"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn layers() {
+        let c: Config = serde_yaml::from_str(
+            r#"---
+layers:
+  - name: nav
+    activate:
+      key: CapsLock
+      mode: momentary
+    bind:
+      - key: [H]
+        action:
+          click:
+            key: [Left]
+  - name: num
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            c.layers.as_slice(),
+            [
+                Layer { name, activate: Some(Activate { key: CapsLock, mode: LayerMode::Momentary }), bind },
+                Layer { activate: None, .. },
+            ] if name == "nav" && bind.len() == 1
+        ));
+        assert!(crate::load_config(
+            "layers:\n  - name: nav\n    bind:\n      - key: [[K], [C]]\n        action: print-pressed-state"
+        )
+        .is_err());
+    }
 }
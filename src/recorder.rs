@@ -0,0 +1,306 @@
+use crate::context::context;
+use crate::details::{started_context, Registry};
+use crate::sequence::Cancel;
+use crate::{Backend, Keyboard, Mouse, SendHandle};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Single event of a `Recording`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Recorded {
+    KeyDown(Keyboard),
+    KeyUp(Keyboard),
+    ButtonDown(Mouse),
+    ButtonUp(Mouse),
+    /// Pointer position, only where the backend reports it.
+    MoveTo(i32, i32),
+    /// Wheel clicks, as in `Mouse::scroll`.
+    Scroll(i32, i32),
+}
+
+/// Recorded event with the milliseconds elapsed since the recording started.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub at: u64,
+    pub event: Recorded,
+}
+
+/// Captures the keyboard, mouse button and pointer events observed by a `Context` until stopped.
+/// Events injected by this library, such as those of a `Sequence` or a remap, are captured too.
+/// ```
+/// use mki::*;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// fn record_and_replay() {
+///   let recorder = Recorder::start();
+///   thread::sleep(Duration::from_secs(10));
+///   let recording = recorder.stop();
+///   recording.save("repro.yaml").unwrap();
+///   Recording::load("repro.yaml").unwrap().play(1.0).join();
+/// }
+/// ```
+pub struct Recorder {
+    registry: Arc<Registry>,
+    id: u64,
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl Recorder {
+    /// Starts recording the events of the default context.
+    pub fn start() -> Self {
+        started_context().record()
+    }
+
+    pub(crate) fn new(registry: Arc<Registry>) -> Self {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let started = Instant::now();
+        let id = registry.add_recorder(Arc::new(Box::new({
+            let events = events.clone();
+            move |event| {
+                events.lock().unwrap().push(RecordedEvent {
+                    at: started.elapsed().as_millis() as u64,
+                    event,
+                })
+            }
+        })));
+        Recorder {
+            registry,
+            id,
+            events,
+        }
+    }
+
+    /// Events recorded so far.
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stop(self) -> Recording {
+        self.registry.remove_recorder(self.id);
+        Recording {
+            events: self.events.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.registry.remove_recorder(self.id);
+    }
+}
+
+/// Events captured by a `Recorder`, can be saved and loaded as YAML or JSON and replayed.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    /// Replays the events on new thread through the backend of the default context
+    /// keeping the original timing, `speed` of 2.0 replays twice as fast.
+    /// Keys and buttons still held once finished or cancelled are released.
+    pub fn play(&self, speed: f64) -> SendHandle {
+        assert!(speed > 0.0, "speed has to be positive");
+        let cloned = self.clone();
+        SendHandle::spawn(move |cancel| cloned.replay(context().backend().as_ref(), speed, cancel))
+    }
+
+    /// Returns false if cancelled.
+    fn replay(&self, backend: &dyn Backend, speed: f64, cancel: &Cancel) -> bool {
+        let started = Instant::now();
+        let mut held_keys = Vec::new();
+        let mut held_buttons = Vec::new();
+        let mut completed = true;
+        for RecordedEvent { at, event } in &self.events {
+            let due = Duration::from_secs_f64(*at as f64 / 1000.0 / speed);
+            if !cancel.sleep(due.saturating_sub(started.elapsed())) {
+                completed = false;
+                break;
+            }
+            match *event {
+                Recorded::KeyDown(key) => {
+                    backend.press(key);
+                    held_keys.push(key);
+                }
+                Recorded::KeyUp(key) => {
+                    backend.release(key);
+                    held_keys.retain(|held| *held != key);
+                }
+                Recorded::ButtonDown(button) => {
+                    backend.press_button(button);
+                    held_buttons.push(button);
+                }
+                Recorded::ButtonUp(button) => {
+                    backend.release_button(button);
+                    held_buttons.retain(|held| *held != button);
+                }
+                Recorded::MoveTo(x, y) => backend.move_to_pixel(x, y),
+                Recorded::Scroll(dx, dy) => backend.scroll(dx, dy),
+            }
+        }
+        for key in held_keys.iter().rev() {
+            backend.release(*key);
+        }
+        for button in held_buttons.iter().rev() {
+            backend.release_button(*button);
+        }
+        completed
+    }
+
+    /// How long the replay takes at the original speed.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.events.last().map(|event| event.at).unwrap_or(0))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(content)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// Writes the recording as JSON if the path ends with `.json`, as YAML otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let content = if is_json(path.as_ref()) {
+            self.to_json().map_err(invalid_data)?
+        } else {
+            self.to_yaml().map_err(invalid_data)?
+        };
+        std::fs::write(path, content)
+    }
+
+    /// Reads a recording written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref())?;
+        if is_json(path.as_ref()) {
+            Self::from_json(&content).map_err(invalid_data)
+        } else {
+            Self::from_yaml(&content).map_err(invalid_data)
+        }
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+fn invalid_data(error: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::recorder::{Recorded, RecordedEvent, Recording};
+    use crate::sequence::Cancel;
+    use crate::Keyboard::*;
+    use crate::{Backend, Context, Injected, MockBackend, Mouse};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn record_and_replay() {
+        let mock = MockBackend::new();
+        let context = Context::builder().backend(mock.clone()).build();
+        context.start();
+        assert!(mock.wait_for_listener(Duration::from_secs(1)));
+        let recorder = context.record();
+        mock.key_down(A);
+        thread::sleep(Duration::from_millis(30));
+        mock.key_up(A);
+        mock.button_down(Mouse::Left);
+        mock.scrolled(0, -2);
+        mock.mouse_moved(3, 4);
+        let recording = recorder.stop();
+        mock.key_down(B);
+        context.shutdown();
+
+        let events: Vec<Recorded> = recording.events.iter().map(|event| event.event).collect();
+        assert_eq!(
+            vec![
+                Recorded::KeyDown(A),
+                Recorded::KeyUp(A),
+                Recorded::ButtonDown(Mouse::Left),
+                Recorded::Scroll(0, -2),
+                Recorded::MoveTo(3, 4),
+            ],
+            events
+        );
+        assert!(recording.events[1].at >= 30);
+
+        let yaml = recording.to_yaml().unwrap();
+        assert_eq!(recording, Recording::from_yaml(&yaml).unwrap());
+        let json = recording.to_json().unwrap();
+        assert_eq!(recording, Recording::from_json(&json).unwrap());
+
+        let replay = MockBackend::new();
+        let started = Instant::now();
+        assert!(recording.replay(&replay, 2.0, &Cancel::default()));
+        assert!(started.elapsed() >= Duration::from_millis(15));
+        assert_eq!(
+            vec![
+                Injected::Press(A),
+                Injected::Release(A),
+                Injected::PressButton(Mouse::Left),
+                Injected::Scroll(0, -2),
+                Injected::MoveTo(3, 4),
+                Injected::ReleaseButton(Mouse::Left),
+            ],
+            replay.injected()
+        );
+    }
+
+    #[test]
+    fn load() {
+        let recording = Recording::from_yaml(
+            "events:\n  - at: 0\n    event:\n      key-down: A\n  - at: 5\n    event:\n      move-to: [1, 2]",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                RecordedEvent {
+                    at: 0,
+                    event: Recorded::KeyDown(A)
+                },
+                RecordedEvent {
+                    at: 5,
+                    event: Recorded::MoveTo(1, 2)
+                },
+            ],
+            recording.events
+        );
+        assert_eq!(Duration::from_millis(5), recording.duration());
+    }
+
+    #[test]
+    fn replayed_moves_are_in_pixels() {
+        let recording = Recording {
+            events: vec![RecordedEvent {
+                at: 0,
+                event: Recorded::MoveTo(1919, 1079),
+            }],
+        };
+        let replay = MockBackend::new();
+        assert!(recording.replay(&replay, 1.0, &Cancel::default()));
+        assert_eq!(vec![Injected::MoveTo(1919, 1079)], replay.injected());
+        assert_eq!((1919, 1079), replay.position());
+    }
+}
@@ -1,47 +1,672 @@
-use crate::Keyboard;
+use crate::context::context;
+use crate::{Backend, Keyboard, Layout, Mouse};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Step {
+    /// Keys pressed together, then released.
+    Chord(Vec<Keyboard>),
+    /// Character without a key, typed through `Backend::type_char`.
+    Char(char),
+    Press(Keyboard),
+    Release(Keyboard),
+    Click(Mouse),
+    PressButton(Mouse),
+    ReleaseButton(Mouse),
+    MoveTo(i32, i32),
+    MoveBy(i32, i32),
+    Scroll(i32, i32),
+    Sleep(u64), // Milliseconds
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
 /// A sequence of events to execute.
+/// Can be built with `SequenceBuilder`, parsed from text or from a string in the style of
+/// AutoHotkey `Send`, see `from_str`. Serializable so that it can be stored and replayed.
 pub struct Sequence {
-    sequence: Vec<Vec<Keyboard>>,
+    sequence: Vec<Step>,
+    #[serde(default = "default_delay", with = "millis")]
+    hold: Duration,
+    #[serde(default = "default_delay", with = "millis")]
+    gap: Duration,
+    #[serde(default, with = "millis")]
+    jitter: Duration,
+}
+
+fn default_delay() -> Duration {
+    Duration::from_millis(15)
+}
+
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
 }
 
 impl Sequence {
+    fn new(sequence: Vec<Step>) -> Self {
+        Sequence {
+            sequence,
+            hold: default_delay(),
+            gap: default_delay(),
+            jitter: Duration::from_millis(0),
+        }
+    }
+
+    pub fn builder() -> SequenceBuilder {
+        SequenceBuilder::default()
+    }
+
     /// A Sequence of events to execute parsed from some text.
-    /// Can only be created if all the keys are supported. upon encountering a key that cannot be
-    /// represented will return None.
+    /// Characters of the layout of the default context are typed with their keys, modifiers included,
+    /// anything else such as diacritics or emoji is typed through `Backend::type_char`.
+    /// Returns None upon encountering a control character other than new line or tab.
     pub fn text(text: &str) -> Option<Self> {
+        Self::text_in(text, &context().layout())
+    }
+
+    /// Version of `text` for given layout.
+    pub fn text_in(text: &str, layout: &Layout) -> Option<Self> {
         let mut sequence = Vec::new();
         for char in text.chars() {
-            let uppercase = char.to_ascii_uppercase();
-            use Keyboard::*;
-            let key = Keyboard::from_str(&uppercase.to_string()).ok()?;
-            if char.is_uppercase() || char == ':' || char == '\"' {
-                sequence.push(vec![LeftShift, key])
-            } else {
-                sequence.push(vec![key])
-            }
+            sequence.push(char_step(char, layout)?);
         }
-        Some(Sequence { sequence })
+        Some(Self::new(sequence))
+    }
+
+    /// How long the keys are held down, 15ms by default.
+    pub fn hold(mut self, hold: Duration) -> Self {
+        self.hold = hold;
+        self
+    }
+
+    /// Pause after every key, 15ms by default.
+    pub fn gap(mut self, gap: Duration) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Random duration up to `jitter` is added to every hold and gap, none by default.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
     }
 
     /// send this Sequence on new thread.
-    pub fn send(&self) {
+    /// ```
+    /// use mki::*;
+    ///
+    /// fn type_then_confirm() {
+    ///   Sequence::text("Hello").unwrap().send().join();
+    ///   Keyboard::Enter.click();
+    /// }
+    /// ```
+    pub fn send(&self) -> SendHandle {
         let cloned = self.clone();
-        thread::spawn(move || {
-            for keys in &cloned.sequence {
-                for key in keys {
-                    key.press();
+        SendHandle::spawn(move |cancel| cloned.play(context().backend().as_ref(), cancel))
+    }
+
+    /// send this Sequence on the current thread, returns once everything was sent.
+    pub fn send_sync(&self) {
+        self.play(context().backend().as_ref(), &Cancel::default());
+    }
+
+    /// Returns false if cancelled, pressed keys and buttons are released either way.
+    fn play(&self, backend: &dyn Backend, cancel: &Cancel) -> bool {
+        let mut held_keys = Vec::new();
+        let mut held_buttons = Vec::new();
+        let mut completed = true;
+        for step in &self.sequence {
+            let gap = match step {
+                Step::Chord(keys) => {
+                    for key in keys {
+                        backend.press(*key);
+                    }
+                    cancel.sleep(self.hold + random_below(self.jitter));
+                    for key in keys {
+                        backend.release(*key);
+                    }
+                    true
+                }
+                Step::Char(c) => {
+                    backend.type_char(*c);
+                    true
+                }
+                Step::Press(key) => {
+                    backend.press(*key);
+                    held_keys.push(*key);
+                    true
+                }
+                Step::Release(key) => {
+                    backend.release(*key);
+                    held_keys.retain(|held| held != key);
+                    true
+                }
+                Step::Click(button) => {
+                    backend.press_button(*button);
+                    cancel.sleep(self.hold + random_below(self.jitter));
+                    backend.release_button(*button);
+                    true
                 }
-                thread::sleep(Duration::from_millis(15));
-                for key in keys {
-                    key.release();
+                Step::PressButton(button) => {
+                    backend.press_button(*button);
+                    held_buttons.push(*button);
+                    true
+                }
+                Step::ReleaseButton(button) => {
+                    backend.release_button(*button);
+                    held_buttons.retain(|held| held != button);
+                    true
+                }
+                Step::MoveTo(x, y) => {
+                    backend.move_to(*x, *y);
+                    true
+                }
+                Step::MoveBy(x, y) => {
+                    backend.move_by(*x, *y);
+                    true
+                }
+                Step::Scroll(dx, dy) => {
+                    backend.scroll(*dx, *dy);
+                    true
+                }
+                Step::Sleep(millis) => {
+                    completed = cancel.sleep(Duration::from_millis(*millis));
+                    false
+                }
+            };
+            if gap {
+                completed = cancel.sleep(self.gap + random_below(self.jitter));
+            }
+            if !completed {
+                break;
+            }
+        }
+        for key in held_keys.iter().rev() {
+            backend.release(*key);
+        }
+        for button in held_buttons.iter().rev() {
+            backend.release_button(*button);
+        }
+        completed
+    }
+}
+
+/// Parses a string in the style of AutoHotkey `Send`, characters are typed as in `Sequence::text`
+/// while commands are enclosed in braces:
+/// * `{Enter}`, `{Ctrl+Shift+T}` - clicks the key or the chord, `{Tab 3}` clicks 3 times.
+/// * `{Ctrl down}`, `{Ctrl up}` - presses or releases the key.
+/// * `{Sleep 50}` - waits for given milliseconds.
+/// * `{Click}`, `{Click Right}`, `{Click 100 200}`, `{Click 100 200 Right}` - clicks, at given position.
+/// * `{Click Left down}`, `{Click Left up}` - presses or releases the button.
+/// * `{Move 100 200}`, `{MoveBy 10 -10}` - moves the pointer.
+/// * `{WheelUp}`, `{WheelDown 3}`, `{WheelLeft}`, `{WheelRight}` - scrolls.
+/// * `{{}`, `{}}` - the braces themselves.
+///
+/// Keys are named as `Keyboard` variants, common short names such as Ctrl, Shift, Alt, Win or Esc work too.
+/// ```
+/// use mki::*;
+///
+/// fn copy() {
+///   let sequence: Sequence = "{Ctrl down}c{Ctrl up}{Sleep 50}{Click Left}".parse().unwrap();
+///   sequence.send();
+/// }
+/// ```
+impl FromStr for Sequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut builder = Sequence::builder();
+        let mut rest = s;
+        while let Some(open) = rest.find('{') {
+            builder = builder.text(&rest[..open]);
+            let command = &rest[open + 1..];
+            // `{}}` types a closing brace, `{}` alone is an empty command.
+            let close = if command.starts_with("}}") {
+                1
+            } else {
+                command
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed brace in: {}", s))?
+            };
+            builder = builder.command(&command[..close])?;
+            rest = &command[close + 1..];
+        }
+        Ok(builder.text(rest).build())
+    }
+}
+
+/// Builds a `Sequence` step by step, mixing keyboard and mouse.
+/// ```
+/// use mki::*;
+/// use std::time::Duration;
+///
+/// fn new_tab() {
+///   Sequence::builder()
+///     .chord(&[Keyboard::LeftControl, Keyboard::T])
+///     .sleep(Duration::from_millis(100))
+///     .text("github.com\n")
+///     .click_at(10, 10, Mouse::Left)
+///     .build()
+///     .send();
+/// }
+/// ```
+#[derive(Default)]
+pub struct SequenceBuilder {
+    sequence: Vec<Step>,
+    layout: Option<Arc<Layout>>,
+}
+
+impl SequenceBuilder {
+    /// Layout used by the following `text`, defaults to the one of the default context.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = Some(Arc::new(layout));
+        self
+    }
+
+    /// Types the text as `Sequence::text` does, control characters other than new line or tab are skipped.
+    pub fn text(mut self, text: &str) -> Self {
+        if text.is_empty() {
+            return self;
+        }
+        let layout = self.layout.clone().unwrap_or_else(|| context().layout());
+        self.sequence
+            .extend(text.chars().filter_map(|char| char_step(char, &layout)));
+        self
+    }
+
+    /// Clicks the key.
+    pub fn key(self, key: Keyboard) -> Self {
+        self.chord(&[key])
+    }
+
+    /// Presses the keys in order, then releases them.
+    pub fn chord(mut self, keys: &[Keyboard]) -> Self {
+        self.sequence.push(Step::Chord(keys.to_vec()));
+        self
+    }
+
+    /// Presses the key, it is released at the latest when the sequence finishes.
+    pub fn press(mut self, key: Keyboard) -> Self {
+        self.sequence.push(Step::Press(key));
+        self
+    }
+
+    pub fn release(mut self, key: Keyboard) -> Self {
+        self.sequence.push(Step::Release(key));
+        self
+    }
+
+    pub fn click(mut self, button: Mouse) -> Self {
+        self.sequence.push(Step::Click(button));
+        self
+    }
+
+    /// Presses the button, it is released at the latest when the sequence finishes.
+    pub fn press_button(mut self, button: Mouse) -> Self {
+        self.sequence.push(Step::PressButton(button));
+        self
+    }
+
+    pub fn release_button(mut self, button: Mouse) -> Self {
+        self.sequence.push(Step::ReleaseButton(button));
+        self
+    }
+
+    pub fn move_to(mut self, x: i32, y: i32) -> Self {
+        self.sequence.push(Step::MoveTo(x, y));
+        self
+    }
+
+    pub fn move_by(mut self, x: i32, y: i32) -> Self {
+        self.sequence.push(Step::MoveBy(x, y));
+        self
+    }
+
+    pub fn click_at(self, x: i32, y: i32, button: Mouse) -> Self {
+        self.move_to(x, y).click(button)
+    }
+
+    /// Wheel clicks, as in `Mouse::scroll`.
+    pub fn scroll(mut self, dx: i32, dy: i32) -> Self {
+        self.sequence.push(Step::Scroll(dx, dy));
+        self
+    }
+
+    /// Waits, no gap is added after the sleep.
+    pub fn sleep(mut self, duration: Duration) -> Self {
+        self.sequence.push(Step::Sleep(duration.as_millis() as u64));
+        self
+    }
+
+    pub fn build(self) -> Sequence {
+        Sequence::new(self.sequence)
+    }
+
+    /// Content of a brace in the `Sequence::from_str` syntax.
+    fn command(self, command: &str) -> Result<Self, String> {
+        if command == "{" || command == "}" {
+            return Ok(self.text(command));
+        }
+        let error = || format!("Unsupported command: {{{}}}", command);
+        let number = |word: &str| word.parse::<i32>().map_err(|_| error());
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let builder = match words.as_slice() {
+            ["Sleep", millis] => {
+                self.sleep(Duration::from_millis(millis.parse().map_err(|_| error())?))
+            }
+            ["Click"] => self.click(Mouse::Left),
+            ["Click", button, "down"] => self.press_button(button_named(button).ok_or_else(error)?),
+            ["Click", button, "up"] => self.release_button(button_named(button).ok_or_else(error)?),
+            ["Click", x, y] => self.click_at(number(x)?, number(y)?, Mouse::Left),
+            ["Click", x, y, button] => self.click_at(
+                number(x)?,
+                number(y)?,
+                button_named(button).ok_or_else(error)?,
+            ),
+            ["Click", button] => self.click(button_named(button).ok_or_else(error)?),
+            ["Move", x, y] => self.move_to(number(x)?, number(y)?),
+            ["MoveBy", x, y] => self.move_by(number(x)?, number(y)?),
+            [wheel] | [wheel, _] if wheel.starts_with("Wheel") => {
+                let clicks = words.get(1).map(|clicks| number(clicks)).unwrap_or(Ok(1))?;
+                match *wheel {
+                    "WheelUp" => self.scroll(0, clicks),
+                    "WheelDown" => self.scroll(0, -clicks),
+                    "WheelLeft" => self.scroll(-clicks, 0),
+                    "WheelRight" => self.scroll(clicks, 0),
+                    _ => return Err(error()),
                 }
-                thread::sleep(Duration::from_millis(15));
             }
+            [key, "down"] => self.press(key_named(key).ok_or_else(error)?),
+            [key, "up"] => self.release(key_named(key).ok_or_else(error)?),
+            [keys] | [keys, _] => {
+                let chord = if keys.len() > 1 && keys.contains('+') {
+                    keys.split('+').map(key_named).collect::<Option<Vec<_>>>()
+                } else {
+                    key_named(keys).map(|key| vec![key])
+                };
+                let chord = chord.ok_or_else(error)?;
+                let times = words.get(1).map(|times| number(times)).unwrap_or(Ok(1))?;
+                (0..times).fold(self, |builder, _| builder.chord(&chord))
+            }
+            _ => return Err(error()),
+        };
+        Ok(builder)
+    }
+}
+
+/// Keys producing the character, or the character itself if there are none.
+fn char_step(char: char, layout: &Layout) -> Option<Step> {
+    if let Some((key, level)) = layout.key(char) {
+        let mut keys = level.modifiers().to_vec();
+        keys.push(key);
+        Some(Step::Chord(keys))
+    } else if char.is_control() {
+        None
+    } else {
+        Some(Step::Char(char))
+    }
+}
+
+fn key_named(name: &str) -> Option<Keyboard> {
+    use Keyboard::*;
+    Some(match name {
+        "Ctrl" | "Control" => LeftControl,
+        "Shift" => LeftShift,
+        "Alt" => LeftAlt,
+        "AltGr" => RightAlt,
+        "Win" => LeftWindows,
+        "Esc" => Escape,
+        "Backspace" => BackSpace,
+        "Del" => Delete,
+        "Ins" => Insert,
+        "PgUp" => PageUp,
+        "PgDn" => PageDown,
+        name => return Keyboard::from_str(name).ok(),
+    })
+}
+
+fn button_named(name: &str) -> Option<Mouse> {
+    Some(match name {
+        "Left" | "L" => Mouse::Left,
+        "Right" | "R" => Mouse::Right,
+        "Middle" | "M" => Mouse::Middle,
+        "Side" | "X1" => Mouse::Side,
+        "Extra" | "X2" => Mouse::Extra,
+        _ => return None,
+    })
+}
+
+/// Returned by `Sequence::send` and `Recording::play`, dropping it lets the sequence finish on its own.
+pub struct SendHandle {
+    handle: JoinHandle<bool>,
+    cancel: Arc<Cancel>,
+}
+
+impl SendHandle {
+    /// Runs `send` on a new thread, it is expected to return false once cancelled.
+    pub(crate) fn spawn(send: impl FnOnce(&Cancel) -> bool + Send + 'static) -> Self {
+        let cancel = Arc::new(Cancel::default());
+        let handle = thread::spawn({
+            let cancel = cancel.clone();
+            move || send(&cancel)
+        });
+        SendHandle { handle, cancel }
+    }
+
+    /// Blocks until the sequence is sent, returns false if it was cancelled.
+    pub fn join(self) -> bool {
+        self.handle.join().unwrap_or(false)
+    }
+
+    /// Stops sending after the current key, nothing is left pressed.
+    pub fn cancel(&self) {
+        self.cancel.cancel()
+    }
+
+    /// Whether everything was sent or sending was cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Cancel {
+    cancelled: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Cancel {
+    pub(crate) fn cancel(&self) {
+        *self.cancelled.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    /// Returns false if cancelled, possibly cutting the sleep short.
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let cancelled = self.cancelled.lock().unwrap();
+        let (cancelled, _) = self
+            .condvar
+            .wait_timeout_while(cancelled, duration, |cancelled| !*cancelled)
+            .unwrap();
+        !*cancelled
+    }
+}
+
+// Jitter does not need to be any good, xorshift seeded by the clock.
+fn random_below(max: Duration) -> Duration {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|since| since.as_nanos() as u64)
+                .unwrap_or_default()
+                | 1,
+        );
+    }
+    if max.as_nanos() == 0 {
+        return max;
+    }
+    let random = STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        x
+    });
+    Duration::from_nanos(random % max.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sequence::{Cancel, Sequence, Step};
+    use crate::Keyboard::*;
+    use crate::{Injected, Layout, MockBackend, Mouse};
+    use std::time::Duration;
+
+    #[test]
+    fn text() {
+        let sequence = Sequence::text_in("a@B_é\n", &Layout::us()).unwrap();
+        assert_eq!(
+            vec![
+                Step::Chord(vec![A]),
+                Step::Chord(vec![LeftShift, Number2]),
+                Step::Chord(vec![LeftShift, B]),
+                Step::Chord(vec![LeftShift, Minus]),
+                Step::Char('é'),
+                Step::Chord(vec![Enter]),
+            ],
+            sequence.sequence
+        );
+        assert_eq!(None, Sequence::text_in("bell\u{7}", &Layout::us()));
+
+        let sequence = Sequence::text_in("z@é", &Layout::de()).unwrap();
+        assert_eq!(
+            vec![
+                Step::Chord(vec![Y]),
+                Step::Chord(vec![RightAlt, Q]),
+                Step::Char('é'),
+            ],
+            sequence.sequence
+        );
+    }
+
+    #[test]
+    fn timing() {
+        let mock = MockBackend::new();
+        let sequence = Sequence::text_in("ab", &Layout::us())
+            .unwrap()
+            .hold(Duration::from_millis(1))
+            .gap(Duration::from_millis(1))
+            .jitter(Duration::from_millis(1));
+        assert!(sequence.play(&mock, &Cancel::default()));
+        assert_eq!(
+            vec![
+                Injected::Press(A),
+                Injected::Release(A),
+                Injected::Press(B),
+                Injected::Release(B),
+            ],
+            mock.injected()
+        );
+
+        mock.clear();
+        let cancel = Cancel::default();
+        cancel.cancel();
+        assert!(!sequence.play(&mock, &cancel));
+        assert_eq!(
+            vec![Injected::Press(A), Injected::Release(A)],
+            mock.injected()
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let sequence: Sequence = Sequence::builder()
+            .layout(Layout::us())
+            .command("Ctrl down")
+            .and_then(|builder| builder.text("c").command("Ctrl up"))
+            .and_then(|builder| builder.command("Sleep 50"))
+            .and_then(|builder| builder.command("Click 1 2 Right"))
+            .and_then(|builder| builder.command("Alt+Tab 2"))
+            .and_then(|builder| builder.command("WheelDown 3"))
+            .and_then(|builder| builder.command("{"))
+            .unwrap()
+            .build();
+        assert_eq!(
+            vec![
+                Step::Press(LeftControl),
+                Step::Chord(vec![C]),
+                Step::Release(LeftControl),
+                Step::Sleep(50),
+                Step::MoveTo(1, 2),
+                Step::Click(Mouse::Right),
+                Step::Chord(vec![LeftAlt, Tab]),
+                Step::Chord(vec![LeftAlt, Tab]),
+                Step::Scroll(0, -3),
+                Step::Chord(vec![LeftShift, LeftBrace]),
+            ],
+            sequence.sequence
+        );
+        assert!("{Ctrl".parse::<Sequence>().is_err());
+        assert!("{Nope}".parse::<Sequence>().is_err());
+        assert!("{Click Left sideways}".parse::<Sequence>().is_err());
+        assert_eq!("}x".parse::<Sequence>(), "{}}x".parse::<Sequence>());
+        assert_eq!(
+            Err("Unsupported command: {}".to_string()),
+            "{}".parse::<Sequence>()
+        );
+        assert_eq!(
+            Err("Unsupported command: {}".to_string()),
+            "{}x".parse::<Sequence>()
+        );
+
+        let yaml = serde_yaml::to_string(&sequence).unwrap();
+        assert_eq!(sequence, serde_yaml::from_str(&yaml).unwrap());
+        let parsed: Sequence =
+            serde_yaml::from_str("sequence:\n  - press: A\n  - sleep: 5").unwrap();
+        assert_eq!(vec![Step::Press(A), Step::Sleep(5)], parsed.sequence);
+        assert_eq!(Duration::from_millis(15), parsed.hold);
+    }
+
+    #[test]
+    fn held_keys_are_released() {
+        let mock = MockBackend::new();
+        let sequence = Sequence::builder()
+            .press(LeftShift)
+            .press_button(Mouse::Left)
+            .sleep(Duration::from_secs(10))
+            .build();
+        let cancel = std::sync::Arc::new(Cancel::default());
+        let canceller = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
         });
+        assert!(!sequence.play(&mock, &cancel));
+        assert_eq!(
+            vec![
+                Injected::Press(LeftShift),
+                Injected::PressButton(Mouse::Left),
+                Injected::Release(LeftShift),
+                Injected::ReleaseButton(Mouse::Left),
+            ],
+            mock.injected()
+        );
     }
 }
@@ -1,31 +1,150 @@
 pub mod keyboard;
 pub mod mouse;
 
-use crate::details::registry;
-use crate::{Event, InhibitEvent, Keyboard, Mouse};
+use crate::mouse::ClickCounter;
+use crate::windows::keyboard::{kimpl, INJECTED};
+use crate::windows::mouse::mimpl;
+use crate::{Backend, InhibitEvent, Keyboard, Listener, Mouse, MultiClick};
 use std::convert::TryInto;
 use std::mem::MaybeUninit;
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
 use winapi::shared::minwindef::{HINSTANCE, LPARAM, LRESULT, WPARAM};
 use winapi::shared::windef::HHOOK__;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
 use winapi::um::winuser::{
-    CallNextHookEx, GetMessageW, SetWindowsHookExW, GET_XBUTTON_WPARAM, KBDLLHOOKSTRUCT, MSG,
-    WH_KEYBOARD_LL, WH_MOUSE_LL, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP,
-    WM_MBUTTONDOWN, WM_MBUTTONUP, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
-    WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
-};
-use winapi::um::winuser::{
-    MSLLHOOKSTRUCT, WM_LBUTTONDBLCLK, WM_MBUTTONDBLCLK, WM_RBUTTONDBLCLK, WM_XBUTTONDBLCLK,
+    CallNextHookEx, GetDoubleClickTime, GetMessageW, GetSystemMetrics, PostThreadMessageW,
+    SetWindowsHookExW, UnhookWindowsHookEx, GET_XBUTTON_WPARAM, KBDLLHOOKSTRUCT, LLKHF_EXTENDED,
+    MSG, MSLLHOOKSTRUCT, SM_CXDOUBLECLK, VK_RETURN, WHEEL_DELTA, WH_KEYBOARD_LL, WH_MOUSE_LL,
+    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
+    WM_MOUSEHWHEEL, WM_MOUSEWHEEL, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN,
+    WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
 };
 
-pub(crate) fn install_hooks() {
-    install_hook(WH_KEYBOARD_LL, keybd_hook);
-    install_hook(WH_MOUSE_LL, mouse_hook);
+lazy_static::lazy_static! {
+    // Hooks do not carry any user data, hence the listener of the running backend is kept here.
+    static ref LISTENER: Mutex<Option<Listener>> = Mutex::new(None);
+    // Low level hooks never receive the WM_*BUTTONDBLCLK messages, the clicks are counted here
+    // along with the last pointer position.
+    static ref CLICKS: Mutex<(ClickCounter, (i32, i32))> = Mutex::new(Default::default());
+}
+
+// Same thresholds Windows uses for the double clicks it reports to the windows.
+fn system_multi_click() -> MultiClick {
+    unsafe {
+        MultiClick {
+            interval: Duration::from_millis(GetDoubleClickTime().into()),
+            tolerance: (GetSystemMetrics(SM_CXDOUBLECLK) / 2).max(0) as u32,
+        }
+    }
+}
+
+fn listener() -> Option<Listener> {
+    LISTENER.lock().unwrap().clone()
+}
+
+/// Backend injecting through SendInput, listening through low level hooks.
+#[derive(Default)]
+pub struct WindowsBackend {
+    thread_id: AtomicU32,
+}
+
+impl Backend for WindowsBackend {
+    fn press(&self, key: Keyboard) {
+        kimpl::press(key)
+    }
+
+    fn release(&self, key: Keyboard) {
+        kimpl::release(key)
+    }
+
+    fn click(&self, key: Keyboard) {
+        kimpl::click(key)
+    }
+
+    fn type_char(&self, c: char) {
+        kimpl::type_char(c)
+    }
+
+    fn is_toggled(&self, key: Keyboard) -> bool {
+        kimpl::is_toggled(key)
+    }
+
+    fn supports_button(&self, button: Mouse) -> bool {
+        !matches!(button, Mouse::Forward | Mouse::Back | Mouse::Task)
+    }
+
+    fn press_button(&self, button: Mouse) {
+        mimpl::press(button)
+    }
+
+    fn release_button(&self, button: Mouse) {
+        mimpl::release(button)
+    }
+
+    fn click_button(&self, button: Mouse) {
+        mimpl::click(button)
+    }
+
+    fn move_to(&self, x: i32, y: i32) {
+        mimpl::move_to(x, y)
+    }
+
+    fn move_to_pixel(&self, x: i32, y: i32) {
+        mimpl::move_to_pixel(x, y)
+    }
+
+    fn move_by(&self, x: i32, y: i32) {
+        mimpl::move_by(x, y)
+    }
+
+    fn scroll(&self, dx: i32, dy: i32) {
+        mimpl::scroll(dx, dy)
+    }
+
+    fn position(&self) -> (i32, i32) {
+        mimpl::position()
+    }
+
+    fn click_at(&self, x: i32, y: i32, button: Mouse) {
+        mimpl::click_at(x, y, button)
+    }
+
+    fn listen(&self, listener: Listener) {
+        *LISTENER.lock().unwrap() = Some(listener);
+        *CLICKS.lock().unwrap() = (ClickCounter::new(system_multi_click()), mimpl::position());
+        self.thread_id
+            .store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+        // For windows hooks need to be installed on the same thread that listens to the Messages.
+        let hooks = install_hooks();
+        process_message();
+        for hook in hooks.iter() {
+            unsafe { UnhookWindowsHookEx(*hook) };
+        }
+        *LISTENER.lock().unwrap() = None;
+    }
+
+    fn stop(&self) {
+        let thread_id = self.thread_id.swap(0, Ordering::Relaxed);
+        if thread_id != 0 {
+            unsafe { PostThreadMessageW(thread_id, WM_QUIT, 0, 0) };
+        }
+    }
+}
+
+fn install_hooks() -> [*mut HHOOK__; 2] {
+    [
+        install_hook(WH_KEYBOARD_LL, keybd_hook),
+        install_hook(WH_MOUSE_LL, mouse_hook),
+    ]
 }
 
-pub(crate) fn process_message() {
+fn process_message() {
     let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
-    unsafe { GetMessageW(&mut msg, null_mut(), 0, 0) };
+    // Returns 0 on WM_QUIT which is posted by stop.
+    while unsafe { GetMessageW(&mut msg, null_mut(), 0, 0) } > 0 {}
 }
 
 fn install_hook(
@@ -40,10 +159,12 @@ unsafe extern "system" fn keybd_hook(
     w_param: WPARAM,
     l_param: LPARAM,
 ) -> LRESULT {
-    let vk: i32 = (*(l_param as *const KBDLLHOOKSTRUCT))
+    let hook_struct = &*(l_param as *const KBDLLHOOKSTRUCT);
+    let vk: i32 = hook_struct
         .vkCode
         .try_into()
         .expect("vkCode does not fit in i32");
+    let injected = hook_struct.dwExtraInfo == INJECTED;
     // https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-keydown
     // Says that we can find the repeat bit here, however that does not apply to lowlvlkb hook which this is.
     // Because IDE is not capable of following to the definition here it is:
@@ -55,16 +176,33 @@ unsafe extern "system" fn keybd_hook(
     //     dwExtraInfo: ULONG_PTR,
     // }}
 
+    let listener = match listener() {
+        Some(listener) => listener,
+        None => return CallNextHookEx(null_mut(), code, w_param, l_param),
+    };
     let mut inhibit = InhibitEvent::No;
     // Note this seemingly is only activated when ALT is not pressed, need to handle WM_SYSKEYDOWN then
     // Test that case.
-    let key: Keyboard = vk.into();
+    // Both Enter keys share the virtual key, the numpad one is extended.
+    let key: Keyboard = if vk == VK_RETURN && hook_struct.flags & LLKHF_EXTENDED != 0 {
+        Keyboard::NumpadEnter
+    } else {
+        vk.into()
+    };
     match w_param as u32 {
         code if code == WM_KEYDOWN || code == WM_SYSKEYDOWN => {
-            inhibit = registry().event_down(Event::Keyboard(key));
+            inhibit = if injected {
+                listener.injected_key_down(key)
+            } else {
+                listener.key_down(key)
+            };
         }
         code if code == WM_KEYUP || code == WM_SYSKEYUP => {
-            inhibit = registry().event_up(Event::Keyboard(key));
+            inhibit = if injected {
+                listener.injected_key_up(key)
+            } else {
+                listener.key_up(key)
+            };
         }
         _ => {}
     }
@@ -76,6 +214,14 @@ unsafe extern "system" fn keybd_hook(
     }
 }
 
+// High resolution wheels report fractions of WHEEL_DELTA, those are summed up until a whole click.
+fn wheel_clicks(accumulated: &AtomicI32, delta: i32) -> i32 {
+    let total = accumulated.fetch_add(delta, Ordering::Relaxed) + delta;
+    let clicks = total / WHEEL_DELTA as i32;
+    accumulated.fetch_sub(clicks * WHEEL_DELTA as i32, Ordering::Relaxed);
+    clicks
+}
+
 unsafe extern "system" fn mouse_hook(
     code: libc::c_int,
     w_param: WPARAM,
@@ -90,6 +236,10 @@ unsafe extern "system" fn mouse_hook(
     //   ULONG_PTR dwExtraInfo;
     // } MSLLHOOKSTRUCT, *LPMSLLHOOKSTRUCT, *PMSLLHOOKSTRUCT;
 
+    let listener = match listener() {
+        Some(listener) => listener,
+        None => return CallNextHookEx(null_mut(), code, w_param, l_param),
+    };
     let data = &*(l_param as *const MSLLHOOKSTRUCT);
     let x_button_param: u16 =
         GET_XBUTTON_WPARAM(data.mouseData.try_into().expect("u32 fits usize"));
@@ -101,53 +251,55 @@ unsafe extern "system" fn mouse_hook(
         None
     };
     let w_param_u32: u32 = w_param.try_into().expect("w_param > u32");
-    registry().update_mouse_position(data.pt.x, data.pt.y);
+    listener.mouse_moved(data.pt.x, data.pt.y);
+    let mut clicks = CLICKS.lock().unwrap();
+    let (counter, last) = &mut *clicks;
+    counter.moved(f64::from(data.pt.x - last.0), f64::from(data.pt.y - last.1));
+    *last = (data.pt.x, data.pt.y);
+    let at = Duration::from_millis(data.time.into());
+    let mut button_down = |button| counter.button_down(&listener, button, at);
+    // For the wheel messages high word of mouseData is the signed delta.
+    let wheel_delta = i32::from((data.mouseData >> 16) as i16);
+    static VERTICAL: AtomicI32 = AtomicI32::new(0);
+    static HORIZONTAL: AtomicI32 = AtomicI32::new(0);
+    let injected = data.dwExtraInfo == INJECTED;
     let inhibit = match w_param_u32 {
-        code if code == WM_LBUTTONDOWN => registry().event_down(Event::Mouse(Mouse::Left)),
-        code if code == WM_LBUTTONDBLCLK => registry().event_click(Event::Mouse(Mouse::DoubleLeft)),
-        code if code == WM_RBUTTONDOWN => registry().event_down(Event::Mouse(Mouse::Right)),
-        code if code == WM_RBUTTONDBLCLK => {
-            registry().event_click(Event::Mouse(Mouse::DoubleRight))
-        }
-        code if code == WM_MBUTTONDOWN => registry().event_down(Event::Mouse(Mouse::Middle)),
-        code if code == WM_MBUTTONDBLCLK => {
-            registry().event_down(Event::Mouse(Mouse::DoubleMiddle))
-        }
+        code if code == WM_MOUSEWHEEL => match wheel_clicks(&VERTICAL, wheel_delta) {
+            0 => InhibitEvent::No,
+            clicks if injected => listener.injected_scrolled(0, clicks),
+            clicks => listener.scrolled(0, clicks),
+        },
+        code if code == WM_MOUSEHWHEEL => match wheel_clicks(&HORIZONTAL, wheel_delta) {
+            0 => InhibitEvent::No,
+            clicks if injected => listener.injected_scrolled(clicks, 0),
+            clicks => listener.scrolled(clicks, 0),
+        },
+        code if code == WM_LBUTTONDOWN => button_down(Mouse::Left),
+        code if code == WM_RBUTTONDOWN => button_down(Mouse::Right),
+        code if code == WM_MBUTTONDOWN => button_down(Mouse::Middle),
         code if code == WM_XBUTTONDOWN => {
             if let Some(x_button) = maybe_x_button {
-                registry().event_down(Event::Mouse(x_button))
-            } else {
-                InhibitEvent::No
-            }
-        }
-        code if code == WM_XBUTTONDBLCLK => {
-            if let Some(x_button) = maybe_x_button {
-                // TODO: figure out the other XButtons.
-                if Mouse::Side == x_button {
-                    registry().event_click(Event::Mouse(Mouse::DoubleSide))
-                } else {
-                    registry().event_click(Event::Mouse(Mouse::DoubleExtra))
-                }
+                button_down(x_button)
             } else {
                 InhibitEvent::No
             }
         }
-        code if code == WM_LBUTTONUP => registry().event_up(Event::Mouse(Mouse::Left)),
-        code if code == WM_LBUTTONUP => registry().event_up(Event::Mouse(Mouse::Left)),
-        code if code == WM_RBUTTONUP => registry().event_up(Event::Mouse(Mouse::Right)),
-        code if code == WM_RBUTTONUP => registry().event_up(Event::Mouse(Mouse::Right)),
-        code if code == WM_MBUTTONUP => registry().event_up(Event::Mouse(Mouse::Middle)),
-        code if code == WM_MBUTTONUP => registry().event_up(Event::Mouse(Mouse::Middle)),
+        code if code == WM_LBUTTONUP => listener.button_up(Mouse::Left),
+        code if code == WM_LBUTTONUP => listener.button_up(Mouse::Left),
+        code if code == WM_RBUTTONUP => listener.button_up(Mouse::Right),
+        code if code == WM_RBUTTONUP => listener.button_up(Mouse::Right),
+        code if code == WM_MBUTTONUP => listener.button_up(Mouse::Middle),
+        code if code == WM_MBUTTONUP => listener.button_up(Mouse::Middle),
         code if code == WM_XBUTTONUP => {
             if let Some(x_button) = maybe_x_button {
-                registry().event_up(Event::Mouse(x_button))
+                listener.button_up(x_button)
             } else {
                 InhibitEvent::No
             }
         }
         code if code == WM_XBUTTONUP => {
             if let Some(x_button) = maybe_x_button {
-                registry().event_up(Event::Mouse(x_button))
+                listener.button_up(x_button)
             } else {
                 InhibitEvent::No
             }
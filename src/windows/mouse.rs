@@ -1,3 +1,4 @@
+use crate::windows::keyboard::INJECTED;
 use crate::Mouse;
 use std::mem;
 use std::mem::size_of;
@@ -5,26 +6,51 @@ use winapi::um::winuser::{
     INPUT_u, SendInput, INPUT, INPUT_MOUSE, LPINPUT, MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_LEFTDOWN,
     MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE,
     MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
-    XBUTTON1, XBUTTON2,
+    WHEEL_DELTA, XBUTTON1, XBUTTON2,
 };
 
 pub(crate) mod mimpl {
     use crate::windows::mouse::{
         button_to_event_down, button_to_mouse_data, mouse_click, mouse_interact_with, mouse_press,
-        mouse_release, Pos,
+        mouse_release, mouse_scroll, Pos,
     };
     use crate::Mouse;
+    use winapi::shared::windef::POINT;
+    use winapi::um::winuser::{
+        GetCursorPos, GetSystemMetrics, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_VIRTUALDESK,
+        MOUSEEVENTF_WHEEL, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+        SM_YVIRTUALSCREEN,
+    };
 
     pub(crate) fn press(button: Mouse) {
-        mouse_press(button)
+        if let Some((dx, dy)) = button.scroll_delta() {
+            scroll(dx, dy);
+        } else {
+            mouse_press(button)
+        }
     }
 
     pub(crate) fn click(button: Mouse) {
-        mouse_click(button);
+        if let Some((dx, dy)) = button.scroll_delta() {
+            scroll(dx, dy);
+        } else {
+            mouse_click(button);
+        }
     }
 
     pub(crate) fn release(button: Mouse) {
-        mouse_release(button);
+        if button.scroll_delta().is_none() {
+            mouse_release(button);
+        }
+    }
+
+    pub(crate) fn scroll(dx: i32, dy: i32) {
+        if dx != 0 {
+            mouse_scroll(MOUSEEVENTF_HWHEEL, dx);
+        }
+        if dy != 0 {
+            mouse_scroll(MOUSEEVENTF_WHEEL, dy);
+        }
     }
 
     // normalized absolute coordinates between 0 and 65,535
@@ -36,11 +62,39 @@ pub(crate) mod mimpl {
         mouse_interact_with(0, 0, Some(Pos::absolute(x, y)));
     }
 
+    // Pixels of the virtual screen as reported by `position`, converted to the normalized range.
+    pub(crate) fn move_to_pixel(x: i32, y: i32) {
+        let normalize = |pixel: i32, origin: i32, size: i32| {
+            ((pixel - origin) as i64 * 65535 / (size - 1).max(1) as i64) as i32
+        };
+        let (x, y) = unsafe {
+            (
+                normalize(
+                    x,
+                    GetSystemMetrics(SM_XVIRTUALSCREEN),
+                    GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                ),
+                normalize(
+                    y,
+                    GetSystemMetrics(SM_YVIRTUALSCREEN),
+                    GetSystemMetrics(SM_CYVIRTUALSCREEN),
+                ),
+            )
+        };
+        mouse_interact_with(MOUSEEVENTF_VIRTUALDESK, 0, Some(Pos::absolute(x, y)));
+    }
+
     // Unlike move_to this uses a human friendly coordinates, 10 is 10 pixels.
     pub(crate) fn move_by(x: i32, y: i32) {
         mouse_interact_with(0, 0, Some(Pos::relative(x, y)));
     }
 
+    pub(crate) fn position() -> (i32, i32) {
+        let mut point = POINT { x: 0, y: 0 };
+        unsafe { GetCursorPos(&mut point) };
+        (point.x, point.y)
+    }
+
     pub(crate) fn click_at(x: i32, y: i32, button: Mouse) {
         mouse_interact_with(
             button_to_event_down(button),
@@ -104,6 +158,28 @@ fn mouse_interact_with(mut interaction: u32, mouse_data: u16, pos: Option<Pos>)
     }
 }
 
+// Positive clicks are up for the WHEEL and right for the HWHEEL.
+// Marked as injected so that the scroll binds do not see their own rotations.
+fn mouse_scroll(wheel: u32, clicks: i32) {
+    unsafe {
+        let mut input: INPUT_u = mem::zeroed();
+        *input.mi_mut() = MOUSEINPUT {
+            dx: 0,
+            dy: 0,
+            mouseData: (clicks * WHEEL_DELTA as i32) as u32,
+            time: 0,
+            dwFlags: wheel,
+            dwExtraInfo: INJECTED,
+        };
+        let mut x = INPUT {
+            type_: INPUT_MOUSE,
+            u: input,
+        };
+
+        SendInput(1, &mut x as LPINPUT, size_of::<INPUT>() as libc::c_int);
+    }
+}
+
 pub fn mouse_press(button: Mouse) {
     let click = button_to_event_down(button) | button_to_event_up(button);
     mouse_interact_with(click, button_to_mouse_data(button), mouse_to_pos(button))
@@ -127,8 +203,8 @@ pub fn mouse_click(button: Mouse) {
 
 fn button_to_mouse_data(button: Mouse) -> u16 {
     match button {
-        Mouse::Side | Mouse::DoubleSide => XBUTTON1,
-        Mouse::Extra | Mouse::DoubleExtra => XBUTTON2,
+        Mouse::Side | Mouse::DoubleSide | Mouse::TripleSide => XBUTTON1,
+        Mouse::Extra | Mouse::DoubleExtra | Mouse::TripleExtra => XBUTTON2,
         _ => 0,
     }
 }
@@ -136,29 +212,37 @@ fn button_to_mouse_data(button: Mouse) -> u16 {
 fn button_to_event_up(button: Mouse) -> u32 {
     use Mouse::*;
     match button {
-        Left | DoubleLeft => MOUSEEVENTF_LEFTDOWN,
-        Right | DoubleRight => MOUSEEVENTF_RIGHTDOWN,
-        Middle | DoubleMiddle => MOUSEEVENTF_MIDDLEDOWN,
-        Side | DoubleSide | Extra | DoubleExtra => MOUSEEVENTF_XDOWN,
+        Left | DoubleLeft | TripleLeft => MOUSEEVENTF_LEFTDOWN,
+        Right | DoubleRight | TripleRight => MOUSEEVENTF_RIGHTDOWN,
+        Middle | DoubleMiddle | TripleMiddle => MOUSEEVENTF_MIDDLEDOWN,
+        Side | DoubleSide | TripleSide | Extra | DoubleExtra | TripleExtra => MOUSEEVENTF_XDOWN,
+        // Linux only.
+        Forward | Back | Task => 0,
+        ScrollUp | ScrollDown | ScrollLeft | ScrollRight => 0,
     }
 }
 
 fn button_to_event_down(button: Mouse) -> u32 {
     use Mouse::*;
     match button {
-        Left | DoubleLeft => MOUSEEVENTF_LEFTUP,
-        Right | DoubleRight => MOUSEEVENTF_RIGHTUP,
-        Middle | DoubleMiddle => MOUSEEVENTF_MIDDLEUP,
-        Side | DoubleSide | Extra | DoubleExtra => MOUSEEVENTF_XUP,
+        Left | DoubleLeft | TripleLeft => MOUSEEVENTF_LEFTUP,
+        Right | DoubleRight | TripleRight => MOUSEEVENTF_RIGHTUP,
+        Middle | DoubleMiddle | TripleMiddle => MOUSEEVENTF_MIDDLEUP,
+        Side | DoubleSide | TripleSide | Extra | DoubleExtra | TripleExtra => MOUSEEVENTF_XUP,
+        // Linux only.
+        Forward | Back | Task => 0,
+        ScrollUp | ScrollDown | ScrollLeft | ScrollRight => 0,
     }
 }
 
 fn mouse_to_pos(button: Mouse) -> Option<Pos> {
     use Mouse::*;
     match button {
-        Left | DoubleLeft => None,
-        Right | DoubleRight => None,
-        Middle | DoubleMiddle => None,
-        Side | DoubleSide | Extra | DoubleExtra => None,
+        Left | DoubleLeft | TripleLeft => None,
+        Right | DoubleRight | TripleRight => None,
+        Middle | DoubleMiddle | TripleMiddle => None,
+        Side | DoubleSide | TripleSide | Extra | DoubleExtra | TripleExtra => None,
+        Forward | Back | Task => None,
+        ScrollUp | ScrollDown | ScrollLeft | ScrollRight => None,
     }
 }